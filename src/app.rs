@@ -1,4 +1,6 @@
-use crate::graphics::{create_graphics, Graphics, Rc};
+use std::path::PathBuf;
+
+use crate::graphics::{create_graphics, Graphics, GraphicsEvent, LoadOptions, Rc, WINDOW_TITLE};
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
@@ -8,18 +10,29 @@ use winit::{
 };
 
 enum State {
-    Ready(Graphics),
-    Init(Option<EventLoopProxy<Graphics>>),
+    Ready(Box<Graphics>),
+    Init(Option<EventLoopProxy<GraphicsEvent>>),
 }
 
 pub struct App {
     state: State,
+    model_path: PathBuf,
+    load_options: LoadOptions,
+    animation: Option<String>,
 }
 
 impl App {
-    pub fn new(event_loop: &EventLoop<Graphics>) -> Self {
+    pub fn new(
+        event_loop: &EventLoop<GraphicsEvent>,
+        model_path: PathBuf,
+        load_options: LoadOptions,
+        animation: Option<String>,
+    ) -> Self {
         Self {
             state: State::Init(Some(event_loop.create_proxy())),
+            model_path,
+            load_options,
+            animation,
         }
     }
 
@@ -36,27 +49,37 @@ impl App {
     }
 }
 
-impl ApplicationHandler<Graphics> for App {
+impl ApplicationHandler<GraphicsEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let State::Init(proxy) = &mut self.state {
-            if let Some(proxy) = proxy.take() {
-                let mut win_attr = Window::default_attributes();
-                win_attr = win_attr.with_title("WGPU GLTF");
+        if let State::Init(proxy) = &mut self.state
+            && let Some(proxy) = proxy.take()
+        {
+            let mut win_attr = Window::default_attributes();
+            win_attr = win_attr.with_title(WINDOW_TITLE);
 
-                let window = Rc::new(
-                    event_loop
-                        .create_window(win_attr)
-                        .expect("create window err."),
-                );
+            let window = Rc::new(
+                event_loop
+                    .create_window(win_attr)
+                    .expect("create window err."),
+            );
 
-                pollster::block_on(create_graphics(window, proxy));
-            }
+            pollster::block_on(create_graphics(window, proxy, self.load_options, self.animation.take()));
         }
     }
 
-    fn user_event(&mut self, _event_loop: &ActiveEventLoop, graphics: Graphics) {
-        graphics.request_redraw();
-        self.state = State::Ready(graphics);
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: GraphicsEvent) {
+        match event {
+            GraphicsEvent::Ready(mut graphics) => {
+                graphics.load_model(&self.model_path);
+                graphics.request_redraw();
+                self.state = State::Ready(graphics);
+            }
+            other => {
+                if let State::Ready(gfx) = &mut self.state {
+                    gfx.handle_graphics_event(other);
+                }
+            }
+        }
     }
 
     fn window_event(
@@ -69,6 +92,11 @@ impl ApplicationHandler<Graphics> for App {
             WindowEvent::Resized(size) => self.resized(size),
             WindowEvent::RedrawRequested => self.draw(),
             WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::DroppedFile(path) => {
+                if let State::Ready(gfx) = &mut self.state {
+                    gfx.load_model(&path);
+                }
+            }
             other => {
                 if let State::Ready(gfx) = &mut self.state {
                     gfx.handle_window_event(&other);
@@ -0,0 +1,286 @@
+use glam::{Quat, Vec3};
+
+use crate::graphics::scene::{SceneGraph, Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    Weights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,
+}
+
+impl From<gltf::animation::Interpolation> for Interpolation {
+    fn from(i: gltf::animation::Interpolation) -> Self {
+        match i {
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        }
+    }
+}
+
+// `values` holds `stride` floats per output element: 3 for translation and scale, 4
+// for rotation (xyzw) and one per morph target for weights. Cubic splines store an
+// in-tangent, value and out-tangent element per keyframe.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: Vec<f32>,
+    pub stride: usize,
+}
+
+impl Channel {
+    // Clamps to the first and last keyframe outside the sampled range.
+    pub fn sample(&self, t: f32) -> Vec<f32> {
+        if self.times.is_empty() {
+            return Vec::new();
+        }
+        let next = self.times.partition_point(|&k| k <= t);
+        if next == 0 || next == self.times.len() {
+            return self.value(if next == 0 { 0 } else { self.times.len() - 1 }).to_vec();
+        }
+        let (prev, t0, t1) = (next - 1, self.times[next - 1], self.times[next]);
+        let dt = t1 - t0;
+        let s = if dt > 0.0 { ((t - t0) / dt).clamp(0.0, 1.0) } else { 0.0 };
+        match self.interpolation {
+            Interpolation::Step => self.value(prev).to_vec(),
+            Interpolation::Linear if self.property == Property::Rotation => {
+                let (a, b) = (Quat::from_slice(self.value(prev)), Quat::from_slice(self.value(next)));
+                a.slerp(b, s).to_array().to_vec()
+            }
+            Interpolation::Linear => {
+                self.value(prev).iter().zip(self.value(next)).map(|(a, b)| a + (b - a) * s).collect()
+            }
+            Interpolation::CubicSpline => {
+                let (s2, s3) = (s * s, s * s * s);
+                let (v0, b0) = (self.element(prev, 1), self.element(prev, 2));
+                let (a1, v1) = (self.element(next, 0), self.element(next, 1));
+                let mut out: Vec<f32> = (0..self.stride)
+                    .map(|i| {
+                        (2.0 * s3 - 3.0 * s2 + 1.0) * v0[i]
+                            + dt * (s3 - 2.0 * s2 + s) * b0[i]
+                            + (-2.0 * s3 + 3.0 * s2) * v1[i]
+                            + dt * (s3 - s2) * a1[i]
+                    })
+                    .collect();
+                if self.property == Property::Rotation {
+                    out = Quat::from_slice(&out).normalize().to_array().to_vec();
+                }
+                out
+            }
+        }
+    }
+
+    fn value(&self, key: usize) -> &[f32] {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.element(key, 1),
+            _ => self.element(key, 0),
+        }
+    }
+
+    // `part` selects in-tangent, value or out-tangent of a cubic spline keyframe.
+    fn element(&self, key: usize, part: usize) -> &[f32] {
+        let per_key = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let start = (key * per_key + part) * self.stride;
+        &self.values[start..start + self.stride]
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+    pub duration: f32,
+}
+
+impl Animation {
+    pub fn apply(&self, t: f32, graph: &mut SceneGraph) {
+        for channel in &self.channels {
+            let Some(node) = graph.node(channel.node) else { continue };
+            let value = channel.sample(t);
+            if value.is_empty() {
+                continue;
+            }
+            let mut local = node.local();
+            match channel.property {
+                Property::Translation => local.translation = Vec3::from_slice(&value),
+                Property::Rotation => local.rotation = Quat::from_slice(&value),
+                Property::Scale => local.scale = Vec3::from_slice(&value),
+                Property::Weights => {
+                    graph.set_weights(channel.node, &value);
+                    continue;
+                }
+            }
+            graph.set_local_transform(channel.node, local);
+        }
+    }
+}
+
+// Plays one animation at a time. Nodes it moved get their rest pose back when another
+// animation is selected.
+#[derive(Debug, Default)]
+pub struct AnimationPlayer {
+    pub animations: Vec<Animation>,
+    current: Option<usize>,
+    time: f32,
+    pub playing: bool,
+    pub looping: bool,
+    rest: Vec<(Transform, Vec<f32>)>,
+}
+
+#[allow(dead_code)]
+impl AnimationPlayer {
+    pub fn new(animations: Vec<Animation>, graph: &SceneGraph) -> Self {
+        let rest = graph.nodes().iter().map(|n| (n.local(), n.weights().to_vec())).collect();
+        Self {
+            current: (!animations.is_empty()).then_some(0),
+            animations,
+            time: 0.0,
+            playing: true,
+            looping: true,
+            rest,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Animation> {
+        self.current.map(|ix| &self.animations[ix])
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn select(&mut self, ix: usize, graph: &mut SceneGraph) -> bool {
+        if ix >= self.animations.len() {
+            return false;
+        }
+        if let Some(prev) = self.current() {
+            for channel in &prev.channels {
+                let Some((local, weights)) = self.rest.get(channel.node) else { continue };
+                match channel.property {
+                    Property::Weights => graph.set_weights(channel.node, weights),
+                    _ => graph.set_local_transform(channel.node, *local),
+                }
+            }
+        }
+        self.current = Some(ix);
+        self.time = 0.0;
+        self.animations[ix].apply(0.0, graph);
+        true
+    }
+
+    pub fn select_by_name(&mut self, name: &str, graph: &mut SceneGraph) -> bool {
+        match self.animations.iter().position(|a| a.name.as_deref() == Some(name)) {
+            Some(ix) => self.select(ix, graph),
+            None => false,
+        }
+    }
+
+    pub fn cycle(&mut self, graph: &mut SceneGraph) {
+        if !self.animations.is_empty() {
+            let next = self.current.map_or(0, |ix| (ix + 1) % self.animations.len());
+            self.select(next, graph);
+        }
+    }
+
+    // Resuming a finished animation that does not loop starts it over.
+    pub fn toggle_playing(&mut self) {
+        self.playing = !self.playing;
+        if self.playing && !self.looping && self.current().is_some_and(|a| self.time >= a.duration) {
+            self.time = 0.0;
+        }
+    }
+
+    // A finished animation that does not loop holds its last frame and pauses.
+    pub fn advance(&mut self, dt: f32, graph: &mut SceneGraph) {
+        let Some(animation) = self.current.map(|ix| &self.animations[ix]) else { return };
+        if !self.playing {
+            return;
+        }
+        self.time += dt;
+        if self.time > animation.duration {
+            if self.looping && animation.duration > 0.0 {
+                self.time %= animation.duration;
+            } else {
+                self.time = animation.duration;
+                self.playing = false;
+            }
+        }
+        animation.apply(self.time, graph);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(property: Property, interpolation: Interpolation, times: &[f32], values: &[f32], stride: usize) -> Channel {
+        Channel { node: 0, property, interpolation, times: times.to_vec(), values: values.to_vec(), stride }
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let c = channel(Property::Translation, Interpolation::Linear, &[1.0, 3.0], &[0.0, 0.0, 0.0, 2.0, 4.0, -6.0], 3);
+        assert_close(&c.sample(2.0), &[1.0, 2.0, -3.0]);
+        assert_close(&c.sample(1.5), &[0.5, 1.0, -1.5]);
+        assert_close(&c.sample(0.0), &[0.0, 0.0, 0.0]);
+        assert_close(&c.sample(3.0), &[2.0, 4.0, -6.0]);
+        assert_close(&c.sample(5.0), &[2.0, 4.0, -6.0]);
+    }
+
+    #[test]
+    fn step() {
+        let c = channel(Property::Weights, Interpolation::Step, &[0.0, 1.0, 2.0], &[10.0, 20.0, 30.0], 1);
+        assert_close(&c.sample(-1.0), &[10.0]);
+        assert_close(&c.sample(0.99), &[10.0]);
+        assert_close(&c.sample(1.0), &[20.0]);
+        assert_close(&c.sample(1.5), &[20.0]);
+        assert_close(&c.sample(2.5), &[30.0]);
+    }
+
+    #[test]
+    fn rotation_slerp() {
+        let (s, c) = std::f32::consts::FRAC_PI_4.sin_cos();
+        let ch = channel(Property::Rotation, Interpolation::Linear, &[0.0, 1.0], &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, s, c], 4);
+        let (s, c) = std::f32::consts::FRAC_PI_8.sin_cos();
+        assert_close(&ch.sample(0.5), &[0.0, 0.0, s, c]);
+        assert_close(&ch.sample(-1.0), &[0.0, 0.0, 0.0, 1.0]);
+    }
+
+    // Keyframes at 0 and 2 hold (in-tangent, value, out-tangent) = (0, 1, 3) and (-1, 5, 0);
+    // tangents are scaled by the 2 second interval.
+    #[test]
+    fn cubic_spline() {
+        let c = channel(Property::Weights, Interpolation::CubicSpline, &[0.0, 2.0], &[0.0, 1.0, 3.0, -1.0, 5.0, 0.0], 1);
+        assert_close(&c.sample(1.0), &[4.0]);
+        assert_close(&c.sample(0.5), &[2.5625]);
+        assert_close(&c.sample(-1.0), &[1.0]);
+        assert_close(&c.sample(2.0), &[5.0]);
+        assert_close(&c.sample(3.0), &[5.0]);
+    }
+
+    #[test]
+    fn no_keyframes() {
+        let c = channel(Property::Scale, Interpolation::Linear, &[], &[], 3);
+        assert!(c.sample(1.0).is_empty());
+    }
+}
@@ -25,6 +25,7 @@ pub fn orbit_eye(yaw: f32, pitch: f32, radius: f32, target: Vec3) -> Vec3 {
     target + dir * radius
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_camera_buffer(
     queue: &Queue,
     camera_buf: &Buffer,
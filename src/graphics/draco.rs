@@ -0,0 +1,166 @@
+use draco_core::{DecoderBuffer, Mesh, MeshDecoder, PointAttribute};
+use gltf::{accessor::DataType, json::{self, validation::USize64, Index}, Semantic};
+use crate::graphics::error::{LoadError, LoadWarning};
+
+pub const DRACO_EXTENSION: &str = "KHR_draco_mesh_compression";
+
+// Decoded attributes and indices are appended as one new buffer and the primitive's
+// accessors are pointed at it, so the rest of the loader reads them like any other.
+pub fn decode_primitives(
+    doc: gltf::Document,
+    buffers: &mut Vec<gltf::buffer::Data>,
+    warnings: &mut Vec<LoadWarning>,
+) -> Result<gltf::Document, LoadError> {
+    let mut decoded = Vec::<(usize, Vec<u8>)>::new();
+    for mesh in doc.meshes() {
+        for prim in mesh.primitives() {
+            let Some(ext) = prim.extension_value(DRACO_EXTENSION) else { continue };
+            match decode_primitive(&doc, &prim, ext, buffers) {
+                Ok(accessors) => decoded.extend(accessors),
+                Err(reason) => {
+                    let (mesh, primitive) = (mesh.index(), prim.index());
+                    let cause = LoadError::DracoDecode { mesh, primitive, reason };
+                    warnings.push(if is_draco_only(&prim) {
+                        LoadWarning::DracoCompressed { mesh, primitive, cause }
+                    } else {
+                        LoadWarning::DracoFallback { mesh, primitive, cause }
+                    });
+                }
+            }
+        }
+    }
+    if decoded.is_empty() {
+        return Ok(doc);
+    }
+
+    let mut root = doc.into_json();
+    let buffer = Index::new(root.buffers.len() as u32);
+    let mut data = Vec::new();
+    for (accessor, bytes) in decoded {
+        data.resize(data.len().next_multiple_of(4), 0);
+        root.buffer_views.push(json::buffer::View {
+            buffer,
+            byte_length: USize64::from(bytes.len()),
+            byte_offset: Some(USize64::from(data.len())),
+            byte_stride: None,
+            name: None,
+            target: None,
+            extensions: None,
+            extras: Default::default(),
+        });
+        let accessor = &mut root.accessors[accessor];
+        accessor.buffer_view = Some(Index::new(root.buffer_views.len() as u32 - 1));
+        accessor.byte_offset = None;
+        data.extend(bytes);
+    }
+    root.buffers.push(json::Buffer {
+        byte_length: USize64::from(data.len()),
+        name: None,
+        uri: None,
+        extensions: None,
+        extras: Default::default(),
+    });
+    buffers.push(gltf::buffer::Data(data));
+    Ok(gltf::Document::from_json_without_validation(root))
+}
+
+// Primitives are readable without decoding only when the exporter also wrote
+// uncompressed accessors; without them POSITION has no data of its own.
+pub fn is_draco_only(prim: &gltf::Primitive) -> bool {
+    prim.extension_value(DRACO_EXTENSION).is_some()
+        && prim
+            .get(&Semantic::Positions)
+            .is_none_or(|a| a.view().is_none() && a.sparse().is_none())
+}
+
+fn decode_primitive(
+    doc: &gltf::Document,
+    prim: &gltf::Primitive,
+    ext: &json::Value,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Vec<(usize, Vec<u8>)>, String> {
+    let view = ext
+        .get("bufferView")
+        .and_then(|v| v.as_u64())
+        .and_then(|ix| doc.views().nth(ix as usize))
+        .ok_or_else(|| format!("{DRACO_EXTENSION} has no valid bufferView"))?;
+    let attributes = ext
+        .get("attributes")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| format!("{DRACO_EXTENSION} has no attributes"))?;
+    let src = buffers
+        .get(view.buffer().index())
+        .and_then(|b| b.get(view.offset()..view.offset() + view.length()))
+        .ok_or_else(|| format!("compressed data is outside buffer {}", view.buffer().index()))?;
+
+    let mut mesh = Mesh::new();
+    MeshDecoder::new().decode(&mut DecoderBuffer::new(src), &mut mesh).map_err(|e| e.to_string())?;
+    let num_points = mesh.num_points();
+
+    let mut decoded = Vec::new();
+    for (name, id) in attributes {
+        // Attributes the primitive does not list have no accessor to fill.
+        let Some(accessor) = prim.attributes().find(|(s, _)| s.to_string() == *name).map(|(_, a)| a) else { continue };
+        let attribute = id
+            .as_u64()
+            .and_then(|id| mesh.attribute_by_unique_id(id as u32))
+            .ok_or_else(|| format!("{name} maps to no attribute of the compressed mesh"))?;
+        if accessor.count() != num_points {
+            return Err(format!("{name} accessor has {} elements but the mesh decodes to {num_points}", accessor.count()));
+        }
+        decoded.push((accessor.index(), attribute_bytes(&accessor, attribute, num_points)?));
+    }
+    if prim.get(&Semantic::Positions).is_some_and(|a| !decoded.iter().any(|(ix, _)| *ix == a.index())) {
+        return Err(format!("{DRACO_EXTENSION} attributes do not include POSITION"));
+    }
+
+    if let Some(accessor) = prim.indices() {
+        let indices: Vec<u32> = mesh.faces().iter().flatten().map(|p| p.0).collect();
+        if accessor.count() != indices.len() {
+            return Err(format!("indices accessor has {} elements but the mesh decodes to {}", accessor.count(), indices.len()));
+        }
+        let size = accessor.data_type().size();
+        if let Some(index) = indices.iter().find(|&&i| size < 4 && i >> (size * 8) != 0) {
+            return Err(format!("index {index} does not fit {:?}", accessor.data_type()));
+        }
+        let bytes = indices.iter().flat_map(|i| i.to_le_bytes()[..size].to_vec()).collect();
+        decoded.push((accessor.index(), bytes));
+    }
+    Ok(decoded)
+}
+
+// Draco hands back integer attributes as stored; accessors declared as float
+// expect them already normalized.
+fn attribute_bytes(accessor: &gltf::Accessor, attribute: &PointAttribute, num_points: usize) -> Result<Vec<u8>, String> {
+    let components = accessor.dimensions().multiplicity();
+    if attribute.num_components() as usize != components {
+        return Err(format!(
+            "accessor {} has {components} components but the compressed attribute has {}",
+            accessor.index(),
+            attribute.num_components()
+        ));
+    }
+    let mut values = attribute.read_f32s(num_points, components);
+    if accessor.data_type() == DataType::F32 && attribute.normalized() {
+        let scale = match attribute.data_type() {
+            draco_core::DataType::Int8 => i8::MAX as f32,
+            draco_core::DataType::Uint8 => u8::MAX as f32,
+            draco_core::DataType::Int16 => i16::MAX as f32,
+            draco_core::DataType::Uint16 => u16::MAX as f32,
+            _ => 1.0,
+        };
+        values.iter_mut().for_each(|v| *v = (*v / scale).max(-1.0));
+    }
+    Ok(encode(accessor.data_type(), values.into_iter()))
+}
+
+fn encode(data_type: DataType, values: impl Iterator<Item = f32>) -> Vec<u8> {
+    match data_type {
+        DataType::I8 => values.flat_map(|v| (v as i8).to_le_bytes()).collect(),
+        DataType::U8 => values.flat_map(|v| (v as u8).to_le_bytes()).collect(),
+        DataType::I16 => values.flat_map(|v| (v as i16).to_le_bytes()).collect(),
+        DataType::U16 => values.flat_map(|v| (v as u16).to_le_bytes()).collect(),
+        DataType::U32 => values.flat_map(|v| (v as u32).to_le_bytes()).collect(),
+        DataType::F32 => values.flat_map(f32::to_le_bytes).collect(),
+    }
+}
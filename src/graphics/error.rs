@@ -0,0 +1,121 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Parse(gltf::Error),
+    Resource { uri: String, error: std::io::Error },
+    MissingAttribute { mesh: usize, primitive: usize, semantic: &'static str },
+    UnsupportedExtension(String),
+    BadAccessor { accessor: usize, reason: String },
+    BadBufferView { view: usize, reason: String },
+    BufferViewDecode { view: usize, reason: String },
+    DracoDecode { mesh: usize, primitive: usize, reason: String },
+    UnsupportedImageFormat { image: usize, format: String },
+    ImageDecode { image: usize, error: image::ImageError },
+    IndexOutOfRange { mesh: usize, primitive: usize, index: u32, vertex_count: usize },
+    NodeCycle { node: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "I/O error: {e}"),
+            LoadError::Parse(e) => write!(f, "invalid glTF: {e}"),
+            LoadError::Resource { uri, error } => write!(f, "cannot read {uri}: {error}"),
+            LoadError::MissingAttribute { mesh, primitive, semantic } => {
+                write!(f, "mesh {mesh} primitive {primitive} has no {semantic} attribute")
+            }
+            LoadError::UnsupportedExtension(name) => write!(f, "required extension {name} is not supported"),
+            LoadError::BadAccessor { accessor, reason } => write!(f, "accessor {accessor}: {reason}"),
+            LoadError::BadBufferView { view, reason } => write!(f, "buffer view {view}: {reason}"),
+            LoadError::BufferViewDecode { view, reason } => write!(f, "cannot decompress buffer view {view}: {reason}"),
+            LoadError::DracoDecode { mesh, primitive, reason } => {
+                write!(f, "cannot decode Draco data of mesh {mesh} primitive {primitive}: {reason}")
+            }
+            LoadError::UnsupportedImageFormat { image, format } => {
+                write!(f, "image {image} has unsupported pixel format {format}")
+            }
+            LoadError::ImageDecode { image, error } => write!(f, "cannot decode image {image}: {error}"),
+            LoadError::IndexOutOfRange { mesh, primitive, index, vertex_count } => write!(
+                f,
+                "mesh {mesh} primitive {primitive} references vertex {index} but only has {vertex_count}"
+            ),
+            LoadError::NodeCycle { node } => write!(f, "node {node} has two parents or is its own ancestor"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::Parse(e) => Some(e),
+            LoadError::Resource { error, .. } => Some(error),
+            LoadError::ImageDecode { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<gltf::Error> for LoadError {
+    fn from(e: gltf::Error) -> Self {
+        match e {
+            gltf::Error::Io(e) => LoadError::Io(e),
+            e => LoadError::Parse(e),
+        }
+    }
+}
+
+// Things the loader worked around instead of failing on.
+#[derive(Debug)]
+pub enum LoadWarning {
+    UnsupportedExtension(String),
+    MissingNormals { mesh: usize, primitive: usize },
+    DracoCompressed { mesh: usize, primitive: usize, cause: LoadError },
+    DracoFallback { mesh: usize, primitive: usize, cause: LoadError },
+    TangentGenerationFailed { mesh: usize, primitive: usize },
+    TexCoordUnavailable { material: usize, set: u32 },
+    ImageFallback { image: usize, cause: LoadError },
+    CompressedViewFallback { view: usize, cause: LoadError },
+    TexturePrecisionReduced { image: usize, missing: wgpu::Features },
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadWarning::UnsupportedExtension(name) => write!(f, "extension {name} is used but ignored"),
+            LoadWarning::MissingNormals { mesh, primitive } => {
+                write!(f, "mesh {mesh} primitive {primitive} has no normals, using +Y")
+            }
+            LoadWarning::DracoCompressed { mesh, primitive, cause } => {
+                write!(f, "mesh {mesh} primitive {primitive} has no uncompressed fallback data, skipped: {cause}")
+            }
+            LoadWarning::DracoFallback { mesh, primitive, cause } => {
+                write!(f, "mesh {mesh} primitive {primitive} uses its uncompressed fallback data: {cause}")
+            }
+            LoadWarning::TangentGenerationFailed { mesh, primitive } => {
+                write!(f, "mesh {mesh} primitive {primitive} has degenerate UVs, tangents are +X")
+            }
+            LoadWarning::TexCoordUnavailable { material, set } => {
+                write!(f, "material {material} samples TEXCOORD_{set}, using TEXCOORD_0 instead")
+            }
+            LoadWarning::ImageFallback { image, cause } => {
+                write!(f, "image {image} is unusable, textures fall back to another source or white: {cause}")
+            }
+            LoadWarning::CompressedViewFallback { view, cause } => {
+                write!(f, "buffer view {view} uses its uncompressed fallback data: {cause}")
+            }
+            LoadWarning::TexturePrecisionReduced { image, missing } => {
+                let names: Vec<&str> = missing.iter_names().map(|(name, _)| name).collect();
+                write!(f, "image {image} is uploaded as Rgba16Float, the adapter lacks {}", names.join(" | "))
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use wgpu::PrimitiveTopology;
+
+use crate::graphics::model::Vertex;
+
+// Triangles as a plain list, with strips unrolled and their winding restored.
+pub fn triangle_list(topology: PrimitiveTopology, indices: &[u32]) -> Option<Cow<'_, [u32]>> {
+    match topology {
+        PrimitiveTopology::TriangleList => Some(Cow::Borrowed(&indices[..indices.len() / 3 * 3])),
+        PrimitiveTopology::TriangleStrip => Some(Cow::Owned(
+            (0..indices.len().saturating_sub(2))
+                .flat_map(|i| match i % 2 {
+                    0 => [indices[i], indices[i + 1], indices[i + 2]],
+                    _ => [indices[i + 1], indices[i], indices[i + 2]],
+                })
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+struct MikkGeometry<'a> {
+    verts: &'a mut [Vertex],
+    triangles: &'a [u32],
+}
+
+impl MikkGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.verts[self.triangles[face * 3 + vert] as usize]
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).pos
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).nrm
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uv
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let ix = self.triangles[face * 3 + vert] as usize;
+        self.verts[ix].tan = tangent;
+    }
+}
+
+// MikkTSpace tangents from positions, normals and the first UV set. Corners that
+// share a vertex get the last tangent written, which matches for welded vertices.
+pub fn generate_tangents(verts: &mut [Vertex], triangles: &[u32]) -> bool {
+    let mut geometry = MikkGeometry { verts, triangles };
+    bevy_mikktspace::generate_tangents(&mut geometry)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalMode {
+    // What the glTF spec asks for: one normal per face.
+    Flat,
+    // Faces sharing a position are averaged when their normals are within the angle.
+    Smooth { max_angle_deg: f32 },
+}
+
+// Builds normals for triangles that have none. Every corner is unwelded first so
+// faces never share a normal by accident, then corners of the same vertex that ended
+// up with the same normal are welded again. Also returns the input vertex each output
+// vertex came from, for data kept outside `Vertex` such as morph targets.
+pub fn generate_normals(verts: &[Vertex], triangles: &[u32], mode: NormalMode) -> (Vec<Vertex>, Vec<u32>, Vec<u32>) {
+    let corner = |i: usize| &verts[triangles[i] as usize];
+    let face_normals: Vec<glam::Vec3> = triangles
+        .chunks_exact(3)
+        .map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|i| glam::Vec3::from(verts[i as usize].pos));
+            (b - a).cross(c - a)
+        })
+        .collect();
+
+    let corner_normals: Vec<glam::Vec3> = match mode {
+        NormalMode::Flat => (0..triangles.len()).map(|i| face_normals[i / 3]).collect(),
+        NormalMode::Smooth { max_angle_deg } => {
+            let min_cos = max_angle_deg.to_radians().cos();
+            let mut faces_at = HashMap::<[u32; 3], Vec<usize>>::new();
+            for i in 0..triangles.len() {
+                faces_at.entry(corner(i).pos.map(f32::to_bits)).or_default().push(i / 3);
+            }
+            (0..triangles.len())
+                .map(|i| {
+                    let own = face_normals[i / 3].normalize_or_zero();
+                    faces_at[&corner(i).pos.map(f32::to_bits)]
+                        .iter()
+                        .map(|&f| face_normals[f])
+                        .filter(|n| n.normalize_or_zero().dot(own) >= min_cos)
+                        .sum()
+                })
+                .collect()
+        }
+    };
+
+    let mut out_verts = Vec::<Vertex>::new();
+    let mut out_indices = Vec::<u32>::with_capacity(triangles.len());
+    let mut sources = Vec::<u32>::new();
+    let mut welded = HashMap::<(u32, [u32; 3]), u32>::new();
+    for (i, n) in corner_normals.into_iter().enumerate() {
+        let mut v = *corner(i);
+        v.nrm = n.try_normalize().unwrap_or(glam::Vec3::Y).to_array();
+        let ix = *welded.entry((triangles[i], v.nrm.map(f32::to_bits))).or_insert_with(|| {
+            out_verts.push(v);
+            sources.push(triangles[i]);
+            out_verts.len() as u32 - 1
+        });
+        out_indices.push(ix);
+    }
+    (out_verts, out_indices, sources)
+}
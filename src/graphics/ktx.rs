@@ -0,0 +1,181 @@
+use std::io::Read;
+
+use basisu::{DecodeFlags, SourceFormat, TargetFormat, Transcoder};
+use ktx2::{DfdBlockBasic, Format, SupercompressionScheme, TransferFunction};
+use wgpu::{AstcBlock, AstcChannel, Features, TextureFormat};
+
+use crate::graphics::error::LoadError;
+use crate::graphics::texture::TexturePixels;
+
+pub const KTX2_MIME_TYPE: &str = "image/ktx2";
+const KTX2_MAGIC: &[u8] = &[0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+// GPU formats a KTX2 payload can be in, and the device features they need.
+pub const COMPRESSION_FEATURES: Features = Features::TEXTURE_COMPRESSION_BC
+    .union(Features::TEXTURE_COMPRESSION_ETC2)
+    .union(Features::TEXTURE_COMPRESSION_ASTC);
+
+pub fn is_ktx2(bytes: &[u8]) -> bool {
+    bytes.starts_with(KTX2_MAGIC)
+}
+
+// Reads a 2D KTX2 texture with the mip levels it carries, undoing Zstandard
+// supercompression. Levels are uploaded as stored when the device samples their
+// format and decoded to RGBA8 when it does not. Basis Universal payloads (ETC1S,
+// UASTC) are transcoded.
+pub fn decode_ktx2(image: usize, bytes: &[u8], features: Features) -> Result<TexturePixels, LoadError> {
+    let unsupported = |format: String| LoadError::UnsupportedImageFormat { image, format };
+    let reader = ktx2::Reader::new(bytes).map_err(|e| decode_error(image, e))?;
+    let header = reader.header();
+    if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count != 1 {
+        return Err(unsupported("KTX2 array, cube map or 3D texture".to_owned()));
+    }
+    let (width, height) = (header.pixel_width, header.pixel_height.max(1));
+    let Some(vk_format) = header.format else {
+        let dfd = reader.dfd_blocks().find_map(|block| DfdBlockBasic::parse(block.data).ok());
+        let srgb = dfd.is_some_and(|dfd| dfd.header.transfer_function == Some(TransferFunction::SRGB));
+        return transcode_basis(image, bytes, srgb, features, (width, height));
+    };
+    let format = wgpu_format(vk_format).ok_or_else(|| unsupported(format!("KTX2 {vk_format:?}")))?;
+    let (block_w, block_h) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap_or(0) as usize;
+
+    let mut mips = Vec::with_capacity(reader.levels().len());
+    for (level, data) in reader.levels().enumerate() {
+        let bytes = match header.supercompression_scheme {
+            None => data.data.to_vec(),
+            Some(SupercompressionScheme::Zstandard) => {
+                let mut out = Vec::with_capacity(data.uncompressed_byte_length as usize);
+                ruzstd::decoding::StreamingDecoder::new(data.data)
+                    .map_err(|e| decode_error(image, e))?
+                    .read_to_end(&mut out)
+                    .map_err(|e| decode_error(image, e))?;
+                out
+            }
+            Some(other) => return Err(unsupported(format!("KTX2 {other:?} supercompression"))),
+        };
+        let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+        let expected = w.div_ceil(block_w) as usize * h.div_ceil(block_h) as usize * block_size;
+        if bytes.len() != expected {
+            return Err(decode_error(image, format!("level {level} has {} bytes, expected {expected}", bytes.len())));
+        }
+        mips.push(bytes);
+    }
+    if features.contains(format.required_features()) && width % block_w == 0 && height % block_h == 0 {
+        return Ok(TexturePixels::Native { format, width, height, mips });
+    }
+
+    // The device cannot sample the format, or cannot take a base level that is not a
+    // whole number of blocks.
+    let rgba = if format.is_srgb() { TextureFormat::Rgba8UnormSrgb } else { TextureFormat::Rgba8Unorm };
+    let mips = mips
+        .iter()
+        .enumerate()
+        .map(|(level, data)| {
+            decompress(format, data, (width >> level).max(1), (height >> level).max(1))
+                .map_err(|e| decode_error(image, format!("{format:?} level {level}: {e}")))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(TexturePixels::Native { format: rgba, width, height, mips })
+}
+
+// ETC1S is a subset of ETC1 and UASTC of ASTC, so each goes to the format it loses
+// least in first. RGBA8 is the fallback when the device samples none of them, the
+// image is not a whole number of blocks or the codec has no block target.
+fn transcode_basis(
+    image: usize,
+    bytes: &[u8],
+    srgb: bool,
+    features: Features,
+    (width, height): (u32, u32),
+) -> Result<TexturePixels, LoadError> {
+    let transcoder = Transcoder::new(bytes).map_err(|e| decode_error(image, format!("Basis Universal: {e:?}")))?;
+    let astc_4x4 = TextureFormat::Astc { block: AstcBlock::B4x4, channel: AstcChannel::Unorm };
+    let astc = (Features::TEXTURE_COMPRESSION_ASTC, TargetFormat::Astc4x4Rgba, astc_4x4);
+    let bc7 = (Features::TEXTURE_COMPRESSION_BC, TargetFormat::Bc7Rgba, TextureFormat::Bc7RgbaUnorm);
+    let etc2 = (Features::TEXTURE_COMPRESSION_ETC2, TargetFormat::Etc2Rgba, TextureFormat::Etc2Rgba8Unorm);
+    let preferred = match transcoder.source_format() {
+        SourceFormat::Etc1s => [etc2, bc7, astc],
+        _ => [astc, bc7, etc2],
+    };
+    let whole_blocks = width % 4 == 0 && height % 4 == 0;
+    let (target, format) = preferred
+        .into_iter()
+        .find(|(feature, target, _)| whole_blocks && features.contains(*feature) && transcoder.supports(*target))
+        .map_or((TargetFormat::Rgba32, TextureFormat::Rgba8Unorm), |(_, target, format)| (target, format));
+    let format = if srgb { format.add_srgb_suffix() } else { format };
+    let mips = (0..transcoder.level_count())
+        .map(|level| {
+            transcoder
+                .transcode(level, target, DecodeFlags::NONE)
+                .map_err(|e| decode_error(image, format!("Basis Universal level {level} to {target:?}: {e:?}")))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(TexturePixels::Native { format, width, height, mips })
+}
+
+// Software decoders for the block formats `wgpu_format` maps, producing RGBA8.
+fn decompress(format: TextureFormat, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, &'static str> {
+    use texture2ddecoder as t;
+    let (w, h) = (width as usize, height as usize);
+    let mut pixels = vec![0u32; w * h];
+    match format.remove_srgb_suffix() {
+        TextureFormat::Bc1RgbaUnorm => t::decode_bc1a(data, w, h, &mut pixels),
+        TextureFormat::Bc3RgbaUnorm => t::decode_bc3(data, w, h, &mut pixels),
+        TextureFormat::Bc7RgbaUnorm => t::decode_bc7(data, w, h, &mut pixels),
+        TextureFormat::Etc2Rgb8Unorm => t::decode_etc2_rgb(data, w, h, &mut pixels),
+        TextureFormat::Etc2Rgb8A1Unorm => t::decode_etc2_rgba1(data, w, h, &mut pixels),
+        TextureFormat::Etc2Rgba8Unorm => t::decode_etc2_rgba8(data, w, h, &mut pixels),
+        TextureFormat::Astc { .. } => {
+            let (block_w, block_h) = format.block_dimensions();
+            t::decode_astc(data, w, h, block_w as usize, block_h as usize, &mut pixels)
+        }
+        _ => Err("no software decoder for this format"),
+    }?;
+    // The decoders pack each pixel as BGRA.
+    Ok(pixels
+        .iter()
+        .flat_map(|p| {
+            let [b, g, r, a] = p.to_le_bytes();
+            [r, g, b, a]
+        })
+        .collect())
+}
+
+fn decode_error(image: usize, e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> LoadError {
+    let error = image::ImageError::Decoding(image::error::DecodingError::new(
+        image::error::ImageFormatHint::Name("KTX2".to_owned()),
+        e,
+    ));
+    LoadError::ImageDecode { image, error }
+}
+
+// Four-channel formats only: the material samples rgba and single or dual channel
+// formats would come out red or red-green.
+fn wgpu_format(format: Format) -> Option<TextureFormat> {
+    let astc = |block, channel| TextureFormat::Astc { block, channel };
+    Some(match format {
+        Format::R8G8B8A8_UNORM => TextureFormat::Rgba8Unorm,
+        Format::R8G8B8A8_SRGB => TextureFormat::Rgba8UnormSrgb,
+        Format::R16G16B16A16_SFLOAT => TextureFormat::Rgba16Float,
+        Format::BC1_RGBA_UNORM_BLOCK => TextureFormat::Bc1RgbaUnorm,
+        Format::BC1_RGBA_SRGB_BLOCK => TextureFormat::Bc1RgbaUnormSrgb,
+        Format::BC3_UNORM_BLOCK => TextureFormat::Bc3RgbaUnorm,
+        Format::BC3_SRGB_BLOCK => TextureFormat::Bc3RgbaUnormSrgb,
+        Format::BC7_UNORM_BLOCK => TextureFormat::Bc7RgbaUnorm,
+        Format::BC7_SRGB_BLOCK => TextureFormat::Bc7RgbaUnormSrgb,
+        Format::ETC2_R8G8B8_UNORM_BLOCK => TextureFormat::Etc2Rgb8Unorm,
+        Format::ETC2_R8G8B8_SRGB_BLOCK => TextureFormat::Etc2Rgb8UnormSrgb,
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => TextureFormat::Etc2Rgb8A1Unorm,
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => TextureFormat::Etc2Rgb8A1UnormSrgb,
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => TextureFormat::Etc2Rgba8Unorm,
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => TextureFormat::Etc2Rgba8UnormSrgb,
+        Format::ASTC_4x4_UNORM_BLOCK => astc(AstcBlock::B4x4, AstcChannel::Unorm),
+        Format::ASTC_4x4_SRGB_BLOCK => astc(AstcBlock::B4x4, AstcChannel::UnormSrgb),
+        Format::ASTC_6x6_UNORM_BLOCK => astc(AstcBlock::B6x6, AstcChannel::Unorm),
+        Format::ASTC_6x6_SRGB_BLOCK => astc(AstcBlock::B6x6, AstcChannel::UnormSrgb),
+        Format::ASTC_8x8_UNORM_BLOCK => astc(AstcBlock::B8x8, AstcChannel::Unorm),
+        Format::ASTC_8x8_SRGB_BLOCK => astc(AstcBlock::B8x8, AstcChannel::UnormSrgb),
+        _ => return None,
+    })
+}
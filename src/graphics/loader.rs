@@ -1,209 +1,891 @@
-use std::path::Path;
-use anyhow::Result;
-use wgpu::{util::{DeviceExt, TextureDataOrder}, BindGroupLayout, Queue, SamplerDescriptor, TextureFormat, TextureUsages, TextureViewDescriptor, TextureDescriptor, TextureDimension, BindGroupEntry, BindingResource};
-use crate::graphics::model::{GpuMesh, Material, Model, Vertex};
+use std::{collections::{hash_map::Entry, HashMap}, io::Read, path::Path};
+use gltf::{accessor::{DataType, Item, Iter}, json::validation::Validate, Semantic};
+use wgpu::{util::DeviceExt, BindGroupLayout, PrimitiveTopology, Queue, Sampler, TextureView, BindGroupEntry, BindingResource};
+use crate::graphics::animation::{Animation, AnimationPlayer, Channel, Interpolation, Property};
+use crate::graphics::draco::{decode_primitives, is_draco_only, DRACO_EXTENSION};
+use crate::graphics::error::{LoadError, LoadWarning};
+use crate::graphics::ktx::{decode_ktx2, is_ktx2, KTX2_MIME_TYPE};
+use crate::graphics::meshopt::{decode_buffer_views, is_placeholder, MESHOPT_EXTENSION};
+use crate::graphics::geometry::{generate_normals, generate_tangents, triangle_list, NormalMode};
+use crate::graphics::model::{
+    create_joints_buffer, create_model_ubo, create_weights_buffer, GpuMesh, Material, MaterialUniform, Model,
+    SkinInfluence, Vertex,
+};
+use crate::graphics::pipeline::Layouts;
+use crate::graphics::resolver::{data_uri_mime_type, read_uri, FsResolver, ResourceResolver};
+use crate::graphics::scene::{SceneGraph, Skin};
+use crate::graphics::texture::{SamplerCache, SamplerKey, TextureCache, TexturePixels};
+
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "KHR_lights_punctual",
+    "KHR_texture_basisu",
+    "EXT_texture_webp",
+    "KHR_mesh_quantization",
+    MESHOPT_EXTENSION,
+    DRACO_EXTENSION,
+];
+// TEXCOORD_n sets carried in `Vertex`.
+const TEXCOORD_SETS: u32 = 2;
+
+struct PrimitiveData {
+    vbuf: wgpu::Buffer,
+    ibuf: wgpu::Buffer,
+    index_count: u32,
+    material_id: usize,
+    topology: PrimitiveTopology,
+    min: glam::Vec3,
+    max: glam::Vec3,
+    // Morph target deltas, laid out as `morph_targets` in shader.wgsl.
+    targets: Option<wgpu::Buffer>,
+    target_count: usize,
+    vertex_count: usize,
+    // JOINTS_n/WEIGHTS_n sets, laid out as `skin_influences` in shader.wgsl.
+    influences: Option<wgpu::Buffer>,
+    influence_sets: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    pub normals: NormalMode,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self { normals: NormalMode::Flat }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadProgress {
+    pub bytes_parsed: usize,
+    pub images_decoded: usize,
+    pub images_total: usize,
+    pub meshes_uploaded: usize,
+    pub meshes_total: usize,
+}
 
 pub async fn load_gltf_model(
     device: &wgpu::Device,
     queue: &Queue,
-    material_bgl: &BindGroupLayout,
+    layouts: &Layouts,
     path: &Path,
-) -> Result<Model> {
-    let (doc, buffers, images) = gltf::import(path)?;
+    options: &LoadOptions,
+    on_progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(Model, Vec<LoadWarning>), LoadError> {
+    let bytes = std::fs::read(path)?;
+    let mut resolver = FsResolver::new(path.parent().unwrap_or(Path::new("")));
+    let (mut model, warnings) =
+        load_gltf_model_from_slice(device, queue, layouts, &bytes, &mut resolver, options, on_progress).await?;
+    model.sources = std::iter::once(path.to_path_buf())
+        .chain(resolver.resolved().iter().cloned())
+        .collect();
+    Ok((model, warnings))
+}
+
+#[allow(dead_code)]
+pub async fn load_gltf_model_from_reader(
+    device: &wgpu::Device,
+    queue: &Queue,
+    layouts: &Layouts,
+    mut reader: impl Read,
+    resolver: &mut dyn ResourceResolver,
+    options: &LoadOptions,
+    on_progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(Model, Vec<LoadWarning>), LoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    load_gltf_model_from_slice(device, queue, layouts, &bytes, resolver, options, on_progress).await
+}
+
+// `bytes` is either a GLB container or glTF JSON; external URIs go through `resolver`.
+pub async fn load_gltf_model_from_slice(
+    device: &wgpu::Device,
+    queue: &Queue,
+    layouts: &Layouts,
+    bytes: &[u8],
+    resolver: &mut dyn ResourceResolver,
+    options: &LoadOptions,
+    on_progress: &mut dyn FnMut(LoadProgress),
+) -> Result<(Model, Vec<LoadWarning>), LoadError> {
+    let mut warnings = Vec::new();
+    let mut progress = LoadProgress::default();
+    let gltf::Gltf { document, blob } = gltf::Gltf::from_slice_without_validation(bytes)?;
+    let doc = validate_document(document, &mut warnings)?;
+    let mut buffers = load_buffers(&doc, blob, resolver)?;
+    decode_buffer_views(&doc, &mut buffers, &mut warnings)?;
+    let doc = decode_primitives(doc, &mut buffers, &mut warnings)?;
+    progress.bytes_parsed = bytes.len() + buffers.iter().map(|b| b.len()).sum::<usize>();
+    progress.images_total = doc.images().len();
+    on_progress(progress);
+
+    let mut images = Vec::<Option<TexturePixels>>::new();
+    for img in doc.images() {
+        let decoded = load_image(&img, &buffers, resolver, device.features());
+        if let Ok(pixels) = &decoded
+            && let Some(missing) = pixels.missing_precision_feature(device.features())
+        {
+            warnings.push(LoadWarning::TexturePrecisionReduced { image: img.index(), missing });
+        }
+        images.push(
+            decoded
+                .map_err(|cause| warnings.push(LoadWarning::ImageFallback { image: img.index(), cause }))
+                .ok(),
+        );
+        progress.images_decoded += 1;
+        on_progress(progress);
+    }
+
+    let material_bgl = &layouts.material_bgl;
     let mut materials = Vec::<Material>::new();
+    let mut samplers = SamplerCache::default();
+    let mut textures = TextureCache::new(images);
     if doc.materials().len() == 0 {
-        materials.push(make_white_material(device, material_bgl));
+        let sampler = samplers.get(device, SamplerKey::default());
+        let view = textures.get(device, queue, None, true);
+        materials.push(make_material(device, material_bgl, &view, &sampler, MaterialUniform::default()));
     } else {
         for m in doc.materials() {
             let pbr = m.pbr_metallic_roughness();
-            let img = pbr
-                .base_color_texture()
-                .and_then(|t| images.get(t.texture().source().index()));
-            materials.push(make_texture_material(device, queue, material_bgl, img));
+            let base_color = pbr.base_color_texture();
+            let image = base_color
+                .as_ref()
+                .and_then(|t| texture_sources(&t.texture()).into_iter().find(|&ix| textures.is_loaded(ix)));
+            let mut texcoord = base_color.as_ref().map_or(0, |t| t.tex_coord());
+            if texcoord >= TEXCOORD_SETS {
+                warnings.push(LoadWarning::TexCoordUnavailable { material: m.index().unwrap_or(0), set: texcoord });
+                texcoord = 0;
+            }
+            let sampler_key = base_color.as_ref().map_or_else(SamplerKey::default, |t| (&t.texture().sampler()).into());
+            let sampler = samplers.get(device, sampler_key);
+            let view = textures.get(device, queue, image, true);
+            let params = MaterialUniform { base_color_texcoord: texcoord, ..Default::default() };
+            materials.push(make_material(device, material_bgl, &view, &sampler, params));
         }
     }
+    let graph = SceneGraph::from_document(&doc)?;
+    let doc_meshes: Vec<gltf::Mesh> = doc.meshes().collect();
+    let roots: Vec<usize> = graph.scenes.iter().flat_map(|s| s.roots.iter().copied()).collect();
+
+    // Meshes are built for every scene up front so switching scenes needs no reload.
+    let mut seen = vec![false; graph.nodes().len()];
+    let mesh_nodes: Vec<(usize, &gltf::Mesh)> = graph
+        .traverse(&roots)
+        .into_iter()
+        .filter(|&ix| !std::mem::replace(&mut seen[ix], true))
+        .filter_map(|ix| graph.nodes()[ix].mesh.map(|m| (ix, &doc_meshes[m])))
+        .collect();
+    let mut unique = vec![false; doc_meshes.len()];
+    progress.meshes_total = mesh_nodes
+        .iter()
+        .filter(|(_, m)| !std::mem::replace(&mut unique[m.index()], true))
+        .map(|(_, m)| m.primitives().len())
+        .sum();
+
+    let skins = read_skins(&doc, &buffers)?;
+    // Bound in place of morph targets and skin influences by primitives without them.
+    let empty_storage = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("empty_storage"),
+        size: std::mem::size_of::<SkinInfluence>() as u64,
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    // None for primitives that cannot be drawn, so each is only reported once.
+    let mut primitives = HashMap::<(usize, usize), Option<PrimitiveData>>::new();
     let mut meshes = Vec::<GpuMesh>::new();
-    let mut min_v = glam::vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
-    let mut max_v = glam::vec3(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
-
-    for scene in doc.scenes() {
-        for node in scene.nodes() {
-            if let Some(mesh) = node.mesh() {
-                for prim in mesh.primitives() {
-                    use gltf::mesh::Mode;
-                    assert!(matches!(prim.mode(), Mode::Triangles), "Only triangles supported");
-
-                    let reader = prim.reader(|buf| Some(&buffers[buf.index()].0));
-
-                    let positions = reader.read_positions().expect("POSITION missing")
-                        .map(|p| [p[0], p[1], p[2]]);
-                    let normals = reader.read_normals()
-                        .map(|it| it.map(|n| [n[0], n[1], n[2]]));
-                    let uvs = reader.read_tex_coords(0)
-                        .map(|tc| tc.into_f32().map(|t| [t[0], t[1]]));
-
-                    let mut verts = Vec::<Vertex>::new();
-                    match (normals, uvs) {
-                        (Some(ns), Some(uvs)) => for ((p, n), uv) in positions.zip(ns).zip(uvs) {
-                            min_v = min_v.min(glam::Vec3::from(p));
-                            max_v = max_v.max(glam::Vec3::from(p));
-                            verts.push(Vertex { pos: p, nrm: n, uv });
-                        },
-                        (Some(ns), None) => for (p, n) in positions.zip(ns) {
-                            min_v = min_v.min(glam::Vec3::from(p));
-                            max_v = max_v.max(glam::Vec3::from(p));
-                            verts.push(Vertex { pos: p, nrm: n, uv: [0.0, 0.0] });
-                        },
-                        (None, Some(uvs)) => for (p, uv) in positions.zip(uvs) {
-                            min_v = min_v.min(glam::Vec3::from(p));
-                            max_v = max_v.max(glam::Vec3::from(p));
-                            verts.push(Vertex { pos: p, nrm: [0.0, 1.0, 0.0], uv });
-                        },
-                        (None, None) => for p in positions {
-                            min_v = min_v.min(glam::Vec3::from(p));
-                            max_v = max_v.max(glam::Vec3::from(p));
-                            verts.push(Vertex { pos: p, nrm: [0.0, 1.0, 0.0], uv: [0.0, 0.0] });
-                        },
-                    }
-
-                    let indices: Vec<u32> = reader
-                        .read_indices()
-                        .map(|r| r.into_u32().collect())
-                        .unwrap_or_else(|| (0..verts.len() as u32).collect());
-
-                    let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("mesh_vbuf"),
-                        contents: bytemuck::cast_slice(&verts),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    });
-                    let ibuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("mesh_ibuf"),
-                        contents: bytemuck::cast_slice(&indices),
-                        usage: wgpu::BufferUsages::INDEX,
-                    });
-
-                    let mat_ix = prim.material().index().unwrap_or(0);
-                    meshes.push(GpuMesh {
-                        vbuf,
-                        ibuf,
-                        index_count: indices.len() as u32,
-                        material_id: mat_ix,
-                    });
+    for (node_ix, mesh) in mesh_nodes {
+        for prim in mesh.primitives() {
+            let data = match primitives.entry((mesh.index(), prim.index())) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    // Already reported when its Draco data failed to decode.
+                    let data = if is_draco_only(&prim) {
+                        None
+                    } else {
+                        Some(upload_primitive(device, mesh, &prim, &buffers, options, &mut warnings)?)
+                    };
+                    progress.meshes_uploaded += 1;
+                    on_progress(progress);
+                    e.insert(data)
                 }
-            }
+            };
+            let Some(data) = data else { continue };
+            let weights = graph.nodes()[node_ix].weights();
+            let weights_buf = create_weights_buffer(device, data.target_count, data.vertex_count, weights);
+            let targets = data.targets.as_ref().unwrap_or(&empty_storage);
+            // A skin only applies to primitives that have joints and weights.
+            let skin = graph.nodes()[node_ix].skin.filter(|&s| data.influence_sets > 0 && s < skins.len());
+            let joint_matrices = skin.map(|s| skins[s].joint_matrices(&graph)).unwrap_or_default();
+            let joints_buf = create_joints_buffer(device, data.influence_sets, &joint_matrices);
+            let influences = data.influences.as_ref().unwrap_or(&empty_storage);
+            let (model_buf, model_bg) = create_model_ubo(
+                device,
+                &layouts.model_bgl,
+                glam::Mat4::IDENTITY,
+                [targets, &weights_buf, influences, &joints_buf],
+            );
+            meshes.push(GpuMesh {
+                vbuf: data.vbuf.clone(),
+                ibuf: data.ibuf.clone(),
+                index_count: data.index_count,
+                material_id: data.material_id,
+                topology: data.topology,
+                node: node_ix,
+                min: data.min,
+                max: data.max,
+                model_buf,
+                model_bg,
+                weights_buf,
+                target_count: data.target_count,
+                joints_buf,
+                skin,
+            });
+        }
+    }
+
+    let animations = AnimationPlayer::new(read_animations(&doc, &buffers)?, &graph);
+    let mut model = Model {
+        meshes,
+        materials,
+        graph,
+        skins,
+        animations,
+        visible: Vec::new(),
+        recommended_xform: glam::Mat4::IDENTITY,
+        sources: Vec::new(),
+    };
+    model.refresh_active_scene(queue);
+    Ok((model, warnings))
+}
+
+// gltf-json rejects any required extension it does not know itself, so the check
+// is done here against what this loader handles before validating the rest.
+fn validate_document(document: gltf::Document, warnings: &mut Vec<LoadWarning>) -> Result<gltf::Document, LoadError> {
+    let mut root = document.into_json();
+    if let Some(ext) = root.extensions_required.iter().find(|e| !SUPPORTED_EXTENSIONS.contains(&e.as_str())) {
+        return Err(LoadError::UnsupportedExtension(ext.clone()));
+    }
+    for ext in root.extensions_used.iter().filter(|e| !SUPPORTED_EXTENSIONS.contains(&e.as_str())) {
+        warnings.push(LoadWarning::UnsupportedExtension(ext.clone()));
+    }
+    root.extensions_required.clear();
+
+    // Accessors of Draco-compressed primitives may carry no data of their own, which
+    // gltf-json reports as a missing bufferView until the Draco data is decoded.
+    let draco_views: Vec<String> = draco_accessors(&root).map(|ix| format!("accessors[{ix}].bufferView")).collect();
+    let mut errors = Vec::new();
+    root.validate(&root, gltf::json::Path::new, &mut |path, error| {
+        let path = path();
+        if !(error == gltf::json::validation::Error::Missing && draco_views.iter().any(|v| v == path.as_str())) {
+            errors.push((path, error));
         }
+    });
+    if !errors.is_empty() {
+        return Err(gltf::Error::Validation(errors).into());
     }
+    Ok(gltf::Document::from_json_without_validation(root))
+}
+
+fn draco_accessors(root: &gltf::json::Root) -> impl Iterator<Item = usize> + '_ {
+    root.meshes
+        .iter()
+        .flat_map(|m| &m.primitives)
+        .filter(|p| p.extensions.as_ref().is_some_and(|e| e.others.contains_key(DRACO_EXTENSION)))
+        .flat_map(|p| p.attributes.values().chain(&p.indices))
+        .map(|ix| ix.value())
+}
 
-    let center = (min_v + max_v) * 0.5;
-    let extent = max_v - min_v;
-    let max_dim = extent.max_element().max(1e-5);
-    let scale   = 1.0 / max_dim;
-    let recommended_xform =
-        glam::Mat4::from_scale(glam::Vec3::splat(scale * 2.0))
-        * glam::Mat4::from_translation(-center);
+fn load_buffers(
+    doc: &gltf::Document,
+    mut blob: Option<Vec<u8>>,
+    resolver: &mut dyn ResourceResolver,
+) -> Result<Vec<gltf::buffer::Data>, LoadError> {
+    let mut buffers = Vec::new();
+    for buffer in doc.buffers() {
+        let data = match buffer.source() {
+            // Filled in by decoding the compressed views that target it.
+            _ if is_placeholder(&buffer) => vec![0; buffer.length()],
+            gltf::buffer::Source::Bin => blob.take().ok_or(gltf::Error::MissingBlob)?,
+            gltf::buffer::Source::Uri(uri) => read_uri(uri, resolver)
+                .map_err(|error| LoadError::Resource { uri: uri.to_owned(), error })?,
+        };
+        if data.len() < buffer.length() {
+            return Err(gltf::Error::BufferLength {
+                buffer: buffer.index(),
+                expected: buffer.length(),
+                actual: data.len(),
+            }
+            .into());
+        }
+        buffers.push(gltf::buffer::Data(data));
+    }
+    Ok(buffers)
+}
 
-    Ok(Model { meshes, materials, recommended_xform })
+// Images a texture can sample, preferred first. Extension sources come before the
+// core `source`, which acts as the fallback when the extension's image is unusable.
+fn texture_sources(texture: &gltf::Texture) -> Vec<usize> {
+    let extension_source = |name| {
+        let source = texture.extension_value(name)?.get("source")?.as_u64()?;
+        Some(source as usize)
+    };
+    extension_source("KHR_texture_basisu")
+        .into_iter()
+        .chain(extension_source("EXT_texture_webp"))
+        .chain(texture.source().map(|s| s.index()))
+        .collect()
 }
 
-fn make_white_material(device: &wgpu::Device, material_bgl: &BindGroupLayout) -> Material {
-    let rgba = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
-    make_texture_material_impl(device, None, Some(rgba), material_bgl)
+fn load_image(
+    img: &gltf::Image,
+    buffers: &[gltf::buffer::Data],
+    resolver: &mut dyn ResourceResolver,
+    features: wgpu::Features,
+) -> Result<TexturePixels, LoadError> {
+    let (encoded, mime_type) = match img.source() {
+        gltf::image::Source::View { view, mime_type } => {
+            let (start, len) = (view.offset(), view.length());
+            let bytes = buffers
+                .get(view.buffer().index())
+                .and_then(|b| b.get(start..start.checked_add(len)?))
+                .ok_or_else(|| LoadError::BadBufferView {
+                    view: view.index(),
+                    reason: format!("bytes {start}..{} are outside buffer {}", start.saturating_add(len), view.buffer().index()),
+                })?;
+            (bytes.to_vec(), Some(mime_type))
+        }
+        gltf::image::Source::Uri { uri, mime_type } => {
+            let bytes = read_uri(uri, resolver).map_err(|error| LoadError::Resource { uri: uri.to_owned(), error })?;
+            (bytes, mime_type.or_else(|| data_uri_mime_type(uri)))
+        }
+    };
+    if mime_type == Some(KTX2_MIME_TYPE) || is_ktx2(&encoded) {
+        return decode_ktx2(img.index(), &encoded, features);
+    }
+    let format = mime_type
+        .and_then(image::ImageFormat::from_mime_type)
+        .or_else(|| image::guess_format(&encoded).ok())
+        .ok_or_else(|| LoadError::UnsupportedImageFormat {
+            image: img.index(),
+            format: mime_type.unwrap_or("unknown encoding").to_owned(),
+        })?;
+    let decoded = image::load_from_memory_with_format(&encoded, format)
+        .map_err(|error| LoadError::ImageDecode { image: img.index(), error })?;
+
+    use gltf::image::Format;
+    let (width, height) = (decoded.width(), decoded.height());
+    let (format, pixels) = match decoded {
+        image::DynamicImage::ImageLuma8(i) => (Format::R8, i.into_raw()),
+        image::DynamicImage::ImageLumaA8(i) => (Format::R8G8, i.into_raw()),
+        image::DynamicImage::ImageRgb8(i) => (Format::R8G8B8, i.into_raw()),
+        image::DynamicImage::ImageRgba8(i) => (Format::R8G8B8A8, i.into_raw()),
+        image::DynamicImage::ImageLuma16(i) => (Format::R16, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        image::DynamicImage::ImageLumaA16(i) => (Format::R16G16, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        image::DynamicImage::ImageRgb16(i) => (Format::R16G16B16, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        image::DynamicImage::ImageRgba16(i) => (Format::R16G16B16A16, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        image::DynamicImage::ImageRgb32F(i) => (Format::R32G32B32FLOAT, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        image::DynamicImage::ImageRgba32F(i) => (Format::R32G32B32A32FLOAT, bytemuck::cast_slice(&i.into_raw()).to_vec()),
+        other => {
+            return Err(LoadError::UnsupportedImageFormat { image: img.index(), format: format!("{:?}", other.color()) });
+        }
+    };
+    decode_pixels(img.index(), &gltf::image::Data { pixels, format, width, height })
 }
 
-fn make_texture_material(
+fn upload_primitive(
     device: &wgpu::Device,
-    queue: &Queue,
-    material_bgl: &BindGroupLayout,
-    img: Option<&gltf::image::Data>,
-) -> Material {
-    let rgba_img = if let Some(g) = img {
-        let (w, h) = (g.width, g.height);
-        match g.format {
-            gltf::image::Format::R8G8B8A8 => {
-                image::RgbaImage::from_raw(w, h, g.pixels.clone())
-                    .unwrap_or_else(|| image::RgbaImage::from_pixel(1, 1, image::Rgba([255,255,255,255])))
-            }
-            gltf::image::Format::R8G8B8 => {
-                let mut out = Vec::with_capacity((w * h * 4) as usize);
-                for c in g.pixels.chunks_exact(3) {
-                    out.extend_from_slice(&[c[0], c[1], c[2], 255]);
-                }
-                image::RgbaImage::from_raw(w, h, out)
-                    .unwrap_or_else(|| image::RgbaImage::from_pixel(1, 1, image::Rgba([255,255,255,255])))
+    mesh: &gltf::Mesh,
+    prim: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    options: &LoadOptions,
+    warnings: &mut Vec<LoadWarning>,
+) -> Result<PrimitiveData, LoadError> {
+    let reader = prim.reader(|buf| buffers.get(buf.index()).map(|b| &b.0[..]));
+
+    let positions: Vec<[f32; 3]> = read_attribute(prim.get(&Semantic::Positions), buffers)?
+        .ok_or(LoadError::MissingAttribute { mesh: mesh.index(), primitive: prim.index(), semantic: "POSITION" })?;
+    let normals: Option<Vec<[f32; 3]>> = read_attribute(prim.get(&Semantic::Normals), buffers)?;
+    let tangents: Option<Vec<[f32; 4]>> = read_attribute(prim.get(&Semantic::Tangents), buffers)?;
+    let uvs: Option<Vec<[f32; 2]>> = read_attribute(prim.get(&Semantic::TexCoords(0)), buffers)?;
+    let uvs1: Option<Vec<[f32; 2]>> = read_attribute(prim.get(&Semantic::TexCoords(1)), buffers)?;
+    let colors = read_colors(prim.get(&Semantic::Colors(0)), buffers)?;
+
+    check_count(prim, Semantic::Normals, normals.as_ref(), positions.len())?;
+    check_count(prim, Semantic::Tangents, tangents.as_ref(), positions.len())?;
+    check_count(prim, Semantic::TexCoords(0), uvs.as_ref(), positions.len())?;
+    check_count(prim, Semantic::TexCoords(1), uvs1.as_ref(), positions.len())?;
+    check_count(prim, Semantic::Colors(0), colors.as_ref(), positions.len())?;
+
+    let mut verts: Vec<Vertex> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, &pos)| Vertex {
+            pos,
+            nrm: normals.as_ref().map_or([0.0, 1.0, 0.0], |n| n[i]),
+            uv: uvs.as_ref().map_or([0.0, 0.0], |t| t[i]),
+            tan: tangents.as_ref().map_or([1.0, 0.0, 0.0, 1.0], |t| t[i]),
+            uv1: uvs1.as_ref().map_or([0.0, 0.0], |t| t[i]),
+            color: colors.as_ref().map_or([1.0, 1.0, 1.0, 1.0], |c| c[i]),
+        })
+        .collect();
+
+    let mut min = glam::vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = glam::vec3(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for v in &verts {
+        min = min.min(glam::Vec3::from(v.pos));
+        max = max.max(glam::Vec3::from(v.pos));
+    }
+
+    let indices: Vec<u32> = match prim.indices() {
+        Some(accessor)
+            if accessor.dimensions() != gltf::accessor::Dimensions::Scalar
+                || !matches!(accessor.data_type(), DataType::U8 | DataType::U16 | DataType::U32) =>
+        {
+            return Err(LoadError::BadAccessor {
+                accessor: accessor.index(),
+                reason: format!("indices are {:?} {:?}", accessor.data_type(), accessor.dimensions()),
+            });
+        }
+        Some(accessor) => reader.read_indices().ok_or_else(|| unreadable(&accessor))?.into_u32().collect(),
+        None => (0..verts.len() as u32).collect(),
+    };
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= verts.len()) {
+        return Err(LoadError::IndexOutOfRange {
+            mesh: mesh.index(),
+            primitive: prim.index(),
+            index,
+            vertex_count: verts.len(),
+        });
+    }
+    let (mut topology, mut indices) = convert_topology(prim.mode(), indices);
+    let mut sources = None;
+    if normals.is_none() {
+        match triangle_list(topology, &indices) {
+            Some(triangles) => {
+                let (generated, welded, from) = generate_normals(&verts, &triangles, options.normals);
+                (verts, indices, sources) = (generated, welded, Some(from));
+                topology = PrimitiveTopology::TriangleList;
             }
-            _ => image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])),
+            None => warnings.push(LoadWarning::MissingNormals { mesh: mesh.index(), primitive: prim.index() }),
         }
+    }
+    if tangents.is_none()
+        && uvs.is_some()
+        && let Some(triangles) = triangle_list(topology, &indices)
+        && !generate_tangents(&mut verts, &triangles)
+    {
+        warnings.push(LoadWarning::TangentGenerationFailed { mesh: mesh.index(), primitive: prim.index() });
+    }
+
+    let target_count = prim.morph_targets().len();
+    let targets = if target_count == 0 {
+        None
     } else {
-        image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]))
+        let deltas = read_morph_targets(prim, buffers, positions.len(), sources.as_deref(), verts.len())?;
+        // Bounds grow by the furthest each target moves a vertex at full weight.
+        for target in deltas.chunks(verts.len().max(1) * 3) {
+            let moves = target.iter().step_by(3).map(|d| glam::Vec3::from_slice(d));
+            min += moves.clone().fold(glam::Vec3::ZERO, glam::Vec3::min);
+            max += moves.fold(glam::Vec3::ZERO, glam::Vec3::max);
+        }
+        Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("morph_targets"),
+            contents: bytemuck::cast_slice(&deltas),
+            usage: wgpu::BufferUsages::STORAGE,
+        }))
     };
 
-    let (w, h) = (rgba_img.width(), rgba_img.height());
-
-    let tex = device.create_texture_with_data(
-        queue,
-        &TextureDescriptor {
-            label: Some("baseColorTex"),
-            size: wgpu::Extent3d { width: w, height: h, depth_or_array_layers: 1 },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        },
-        TextureDataOrder::LayerMajor,
-        rgba_img.as_raw(),
-    );
-
-    let view = tex.create_view(&TextureViewDescriptor::default());
-    let sampler = device.create_sampler(&SamplerDescriptor {
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Linear,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        address_mode_u: wgpu::AddressMode::Repeat,
-        address_mode_v: wgpu::AddressMode::Repeat,
-        address_mode_w: wgpu::AddressMode::Repeat,
-        ..Default::default()
+    let (influences, influence_sets) = read_influences(prim, buffers, positions.len(), sources.as_deref(), verts.len())?;
+    let influences = (influence_sets > 0).then(|| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("skin_influences"),
+            contents: bytemuck::cast_slice(&influences),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
     });
 
-    let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("material_bg"),
-        layout: material_bgl,
-        entries: &[
-            BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&view) },
-            BindGroupEntry { binding: 1, resource: BindingResource::Sampler(&sampler) },
-        ],
+    let vbuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("mesh_vbuf"),
+        contents: bytemuck::cast_slice(&verts),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let ibuf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("mesh_ibuf"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
     });
 
-    Material { bind_group: bg }
+    Ok(PrimitiveData {
+        vbuf,
+        ibuf,
+        index_count: indices.len() as u32,
+        material_id: prim.material().index().unwrap_or(0),
+        topology,
+        min,
+        max,
+        targets,
+        target_count,
+        vertex_count: verts.len(),
+        influences,
+        influence_sets,
+    })
+}
+
+// Every JOINTS_n/WEIGHTS_n pair of each output vertex, set after set, and how many
+// sets there are. Sets stop at the first one missing either accessor.
+fn read_influences(
+    prim: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    count: usize,
+    sources: Option<&[u32]>,
+    vertex_count: usize,
+) -> Result<(Vec<SkinInfluence>, u32), LoadError> {
+    let reader = prim.reader(|buf| buffers.get(buf.index()).map(|b| &b.0[..]));
+    let mut sets = Vec::new();
+    for set in 0.. {
+        let (Some(joints), Some(weights)) = (prim.get(&Semantic::Joints(set)), prim.get(&Semantic::Weights(set))) else {
+            break;
+        };
+        let bad_accessor = |accessor: &gltf::Accessor, what: &str| LoadError::BadAccessor {
+            accessor: accessor.index(),
+            reason: format!("{what} is {:?} {:?}", accessor.data_type(), accessor.dimensions()),
+        };
+        let vec4 = |a: &gltf::Accessor| a.dimensions() == gltf::accessor::Dimensions::Vec4;
+        if !vec4(&joints) || !matches!(joints.data_type(), DataType::U8 | DataType::U16) {
+            return Err(bad_accessor(&joints, "JOINTS"));
+        }
+        if !vec4(&weights) || !matches!(weights.data_type(), DataType::U8 | DataType::U16 | DataType::F32) {
+            return Err(bad_accessor(&weights, "WEIGHTS"));
+        }
+        let j: Vec<[u16; 4]> = reader.read_joints(set).map(|r| r.into_u16().collect()).unwrap_or_default();
+        let w: Vec<[f32; 4]> = reader.read_weights(set).map(|r| r.into_f32().collect()).unwrap_or_default();
+        for (accessor, len) in [(&joints, j.len()), (&weights, w.len())] {
+            if len != count {
+                return Err(LoadError::BadAccessor {
+                    accessor: accessor.index(),
+                    reason: format!("skin set {set} has {len} elements but POSITION has {count}"),
+                });
+            }
+        }
+        sets.push((j, w));
+    }
+
+    let mut influences = Vec::with_capacity(vertex_count * sets.len());
+    for v in 0..vertex_count {
+        let source = sources.map_or(v, |s| s[v] as usize);
+        for (joints, weights) in &sets {
+            influences.push(SkinInfluence { joints: joints[source].map(u32::from), weights: weights[source] });
+        }
+    }
+    Ok((influences, sets.len() as u32))
+}
+
+fn read_animations(doc: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Result<Vec<Animation>, LoadError> {
+    let mut animations = Vec::new();
+    for animation in doc.animations() {
+        let mut channels = Vec::new();
+        for channel in animation.channels() {
+            let sampler = channel.sampler();
+            let (input, output) = (sampler.input(), sampler.output());
+            let times = read_scalars(input.clone(), buffers)?;
+            if times.is_empty() {
+                return Err(LoadError::BadAccessor { accessor: input.index(), reason: "no keyframes".to_owned() });
+            }
+            let vec3 = || read_attribute::<3>(Some(output.clone()), buffers)?.ok_or_else(|| unreadable(&output));
+            let vec4 = || read_attribute::<4>(Some(output.clone()), buffers)?.ok_or_else(|| unreadable(&output));
+            let (property, values) = match channel.target().property() {
+                gltf::animation::Property::Translation => (Property::Translation, vec3()?.concat()),
+                gltf::animation::Property::Rotation => (Property::Rotation, vec4()?.concat()),
+                gltf::animation::Property::Scale => (Property::Scale, vec3()?.concat()),
+                gltf::animation::Property::MorphTargetWeights => (Property::Weights, read_scalars(output.clone(), buffers)?),
+            };
+            let interpolation = sampler.interpolation().into();
+            let elements = times.len() * if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+            let stride = match property {
+                Property::Translation | Property::Scale => 3,
+                Property::Rotation => 4,
+                Property::Weights => values.len().checked_div(elements).unwrap_or(0),
+            };
+            if times.windows(2).any(|w| w[0] > w[1]) {
+                return Err(LoadError::BadAccessor { accessor: input.index(), reason: "keyframe times decrease".to_owned() });
+            }
+            if values.len() != elements * stride || stride == 0 {
+                return Err(LoadError::BadAccessor {
+                    accessor: output.index(),
+                    reason: format!("{} output values for {} keyframes", values.len(), times.len()),
+                });
+            }
+            let node = channel.target().node().index();
+            channels.push(Channel { node, property, interpolation, times, values, stride });
+        }
+        let duration = channels.iter().filter_map(|c| c.times.last()).fold(0.0, |a: f32, &b| a.max(b));
+        animations.push(Animation { name: animation.name().map(str::to_owned), channels, duration });
+    }
+    Ok(animations)
+}
+
+fn read_skins(doc: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Result<Vec<Skin>, LoadError> {
+    doc.skins()
+        .map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|j| j.index()).collect();
+            let reader = skin.reader(|buf| buffers.get(buf.index()).map(|b| &b.0[..]));
+            let mut inverse_bind_matrices: Vec<glam::Mat4> = reader
+                .read_inverse_bind_matrices()
+                .map(|m| m.map(|m| glam::Mat4::from_cols_array_2d(&m)).collect())
+                .unwrap_or_default();
+            if let Some(accessor) = skin.inverse_bind_matrices()
+                && inverse_bind_matrices.len() < joints.len()
+            {
+                return Err(LoadError::BadAccessor {
+                    accessor: accessor.index(),
+                    reason: format!("{} inverse bind matrices for {} joints", inverse_bind_matrices.len(), joints.len()),
+                });
+            }
+            inverse_bind_matrices.resize(joints.len(), glam::Mat4::IDENTITY);
+            Ok(Skin { name: skin.name().map(str::to_owned), joints, inverse_bind_matrices })
+        })
+        .collect()
+}
+
+// Position, normal and tangent deltas of every output vertex for each target in turn,
+// zero where a target leaves an attribute alone. `sources` maps output vertices back
+// to the accessors' when normal generation rebuilt the vertices.
+fn read_morph_targets(
+    prim: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    count: usize,
+    sources: Option<&[u32]>,
+    vertex_count: usize,
+) -> Result<Vec<[f32; 4]>, LoadError> {
+    let mut deltas = Vec::with_capacity(prim.morph_targets().len() * vertex_count * 3);
+    for target in prim.morph_targets() {
+        let mut attributes = Vec::new();
+        for accessor in [target.positions(), target.normals(), target.tangents()] {
+            let data: Option<Vec<[f32; 3]>> = read_attribute(accessor.clone(), buffers)?;
+            if let (Some(d), Some(accessor)) = (&data, accessor)
+                && d.len() != count
+            {
+                return Err(LoadError::BadAccessor {
+                    accessor: accessor.index(),
+                    reason: format!("morph target has {} elements but POSITION has {count}", d.len()),
+                });
+            }
+            attributes.push(data);
+        }
+        for v in 0..vertex_count {
+            let source = sources.map_or(v, |s| s[v] as usize);
+            for attribute in &attributes {
+                let [x, y, z] = attribute.as_ref().map_or([0.0; 3], |d| d[source]);
+                deltas.push([x, y, z, 0.0]);
+            }
+        }
+    }
+    Ok(deltas)
+}
+
+// Float attributes, or with KHR_mesh_quantization any 8 or 16-bit integer type,
+// normalized or not. Integers are dequantized here. For positions the node transform
+// carries any scale and offset the exporter applied; quantized texture coordinates
+// rely on KHR_texture_transform for theirs, which is not applied.
+fn read_attribute<const N: usize>(
+    accessor: Option<gltf::Accessor>,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Option<Vec<[f32; N]>>, LoadError>
+where
+    [f32; N]: Item,
+    [i8; N]: Item,
+    [u8; N]: Item,
+    [i16; N]: Item,
+    [u16; N]: Item,
+{
+    let Some(accessor) = accessor else { return Ok(None) };
+    let bad_accessor = |reason: String| LoadError::BadAccessor { accessor: accessor.index(), reason };
+    if accessor.dimensions().multiplicity() != N {
+        return Err(bad_accessor(format!("expected {N} components, found {:?}", accessor.dimensions())));
+    }
+    let get = |buf: gltf::Buffer| buffers.get(buf.index()).map(|b| &b.0[..]);
+    let normalized = accessor.normalized();
+    let source = accessor.clone();
+    let values = match accessor.data_type() {
+        DataType::F32 => dequantize(Iter::<[f32; N]>::new(accessor, get), |c| c),
+        DataType::I8 if normalized => dequantize(Iter::<[i8; N]>::new(accessor, get), |c| (c as f32 / 127.0).max(-1.0)),
+        DataType::U8 if normalized => dequantize(Iter::<[u8; N]>::new(accessor, get), |c| c as f32 / 255.0),
+        DataType::I16 if normalized => dequantize(Iter::<[i16; N]>::new(accessor, get), |c| (c as f32 / 32767.0).max(-1.0)),
+        DataType::U16 if normalized => dequantize(Iter::<[u16; N]>::new(accessor, get), |c| c as f32 / 65535.0),
+        DataType::I8 => dequantize(Iter::<[i8; N]>::new(accessor, get), f32::from),
+        DataType::U8 => dequantize(Iter::<[u8; N]>::new(accessor, get), f32::from),
+        DataType::I16 => dequantize(Iter::<[i16; N]>::new(accessor, get), f32::from),
+        DataType::U16 => dequantize(Iter::<[u16; N]>::new(accessor, get), f32::from),
+        other => return Err(bad_accessor(format!("{other:?} components cannot be read as floats"))),
+    };
+    values.ok_or_else(|| unreadable(&source)).map(Some)
+}
+
+// COLOR_0 is RGB or RGBA, float or normalized unsigned; RGB gets an opaque alpha.
+fn read_colors(accessor: Option<gltf::Accessor>, buffers: &[gltf::buffer::Data]) -> Result<Option<Vec<[f32; 4]>>, LoadError> {
+    if let Some(a) = &accessor
+        && !matches!((a.data_type(), a.normalized()), (DataType::F32, _) | (DataType::U8 | DataType::U16, true))
+    {
+        let normalized = if a.normalized() { "normalized" } else { "non-normalized" };
+        return Err(LoadError::BadAccessor {
+            accessor: a.index(),
+            reason: format!("COLOR has {normalized} {:?} components, expected float or normalized U8/U16", a.data_type()),
+        });
+    }
+    match accessor {
+        Some(a) if a.dimensions() == gltf::accessor::Dimensions::Vec3 => {
+            Ok(read_attribute::<3>(Some(a), buffers)?.map(|c| c.into_iter().map(|[r, g, b]| [r, g, b, 1.0]).collect()))
+        }
+        accessor => read_attribute::<4>(accessor, buffers),
+    }
+}
+
+// For accessors whose data `Iter::new` could not reach, so a broken accessor is not
+// mistaken for an absent one.
+fn unreadable(accessor: &gltf::Accessor) -> LoadError {
+    LoadError::BadAccessor {
+        accessor: accessor.index(),
+        reason: "data is missing or lies outside its buffer view or buffer".to_owned(),
+    }
+}
+
+// Scalar counterpart of `read_attribute`, for animation inputs and morph weights.
+fn read_scalars(accessor: gltf::Accessor, buffers: &[gltf::buffer::Data]) -> Result<Vec<f32>, LoadError> {
+    if accessor.dimensions() != gltf::accessor::Dimensions::Scalar {
+        return Err(LoadError::BadAccessor {
+            accessor: accessor.index(),
+            reason: format!("expected scalars, found {:?}", accessor.dimensions()),
+        });
+    }
+    fn read<T: Item>(accessor: gltf::Accessor, buffers: &[gltf::buffer::Data], f: impl Fn(T) -> f32) -> Result<Vec<f32>, LoadError> {
+        let source = accessor.clone();
+        Iter::<T>::new(accessor, |buf| buffers.get(buf.index()).map(|b| &b.0[..]))
+            .map(|iter| iter.map(f).collect())
+            .ok_or_else(|| unreadable(&source))
+    }
+    let normalized = accessor.normalized();
+    match accessor.data_type() {
+        DataType::F32 => read(accessor, buffers, |c: f32| c),
+        DataType::I8 if normalized => read(accessor, buffers, |c: i8| (c as f32 / 127.0).max(-1.0)),
+        DataType::U8 if normalized => read(accessor, buffers, |c: u8| c as f32 / 255.0),
+        DataType::I16 if normalized => read(accessor, buffers, |c: i16| (c as f32 / 32767.0).max(-1.0)),
+        DataType::U16 if normalized => read(accessor, buffers, |c: u16| c as f32 / 65535.0),
+        DataType::I8 => read::<i8>(accessor, buffers, f32::from),
+        DataType::U8 => read::<u8>(accessor, buffers, f32::from),
+        DataType::I16 => read::<i16>(accessor, buffers, f32::from),
+        DataType::U16 => read::<u16>(accessor, buffers, f32::from),
+        other => Err(LoadError::BadAccessor {
+            accessor: accessor.index(),
+            reason: format!("{other:?} components cannot be read as floats"),
+        }),
+    }
+}
+
+fn dequantize<T: Copy, const N: usize>(iter: Option<Iter<[T; N]>>, f: impl Fn(T) -> f32) -> Option<Vec<[f32; N]>>
+where
+    [T; N]: Item,
+{
+    Some(iter?.map(|v| v.map(&f)).collect())
+}
+
+fn check_count<T>(prim: &gltf::Primitive, semantic: Semantic, data: Option<&Vec<T>>, expected: usize) -> Result<(), LoadError> {
+    match (data, prim.get(&semantic)) {
+        (Some(d), Some(accessor)) if d.len() != expected => Err(LoadError::BadAccessor {
+            accessor: accessor.index(),
+            reason: format!("{semantic:?} has {} elements but POSITION has {expected}", d.len()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Loops and fans have no wgpu equivalent and are expanded into lists; strips are
+// drawn natively.
+fn convert_topology(mode: gltf::mesh::Mode, indices: Vec<u32>) -> (PrimitiveTopology, Vec<u32>) {
+    use gltf::mesh::Mode;
+    match mode {
+        Mode::Points => (PrimitiveTopology::PointList, indices),
+        Mode::Lines => (PrimitiveTopology::LineList, indices),
+        Mode::LineStrip => (PrimitiveTopology::LineStrip, indices),
+        Mode::LineLoop => {
+            let n = indices.len();
+            let list = if n < 2 {
+                Vec::new()
+            } else {
+                (0..n).flat_map(|i| [indices[i], indices[(i + 1) % n]]).collect()
+            };
+            (PrimitiveTopology::LineList, list)
+        }
+        Mode::Triangles => (PrimitiveTopology::TriangleList, indices),
+        Mode::TriangleStrip => (PrimitiveTopology::TriangleStrip, indices),
+        Mode::TriangleFan => {
+            let list = (1..indices.len().saturating_sub(1))
+                .flat_map(|i| [indices[i], indices[i + 1], indices[0]])
+                .collect();
+            (PrimitiveTopology::TriangleList, list)
+        }
+    }
+}
+
+fn decode_pixels(image: usize, g: &gltf::image::Data) -> Result<TexturePixels, LoadError> {
+    use gltf::image::Format;
+    let short = || LoadError::UnsupportedImageFormat { image, format: format!("{:?} with a short pixel buffer", g.format) };
+    let (w, h) = (g.width, g.height);
+    let channels = match g.format {
+        Format::R8 | Format::R16 => 1,
+        Format::R8G8 | Format::R16G16 => 2,
+        Format::R8G8B8 | Format::R16G16B16 | Format::R32G32B32FLOAT => 3,
+        Format::R8G8B8A8 | Format::R16G16B16A16 | Format::R32G32B32A32FLOAT => 4,
+    };
+    match g.format {
+        Format::R8 | Format::R8G8 | Format::R8G8B8 | Format::R8G8B8A8 => {
+            let pixels: Vec<u8> = g.pixels.chunks_exact(channels).flat_map(|c| expand_rgba(c, 255)).collect();
+            image::RgbaImage::from_raw(w, h, pixels).map(TexturePixels::Unorm8).ok_or_else(short)
+        }
+        Format::R16 | Format::R16G16 | Format::R16G16B16 | Format::R16G16B16A16 => {
+            let values: Vec<u16> = bytemuck::pod_collect_to_vec(&g.pixels);
+            let pixels: Vec<u16> = values.chunks_exact(channels).flat_map(|c| expand_rgba(c, u16::MAX)).collect();
+            image::ImageBuffer::from_raw(w, h, pixels).map(TexturePixels::Unorm16).ok_or_else(short)
+        }
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => {
+            let values: Vec<f32> = bytemuck::pod_collect_to_vec(&g.pixels);
+            let pixels: Vec<f32> = values.chunks_exact(channels).flat_map(|c| expand_rgba(c, 1.0)).collect();
+            image::Rgba32FImage::from_raw(w, h, pixels).map(TexturePixels::Float).ok_or_else(short)
+        }
+    }
 }
 
-fn make_texture_material_impl(
+// Gray and gray+alpha expand to RGB, the way image decoders present them.
+fn expand_rgba<T: Copy>(c: &[T], opaque: T) -> [T; 4] {
+    match *c {
+        [l] => [l, l, l, opaque],
+        [l, a] => [l, l, l, a],
+        [r, g, b] => [r, g, b, opaque],
+        [r, g, b, a, ..] => [r, g, b, a],
+        [] => [opaque; 4],
+    }
+}
+
+fn make_material(
     device: &wgpu::Device,
-    _queue: Option<&Queue>,
-    rgba: Option<image::RgbaImage>,
     material_bgl: &BindGroupLayout,
+    base_color: &TextureView,
+    sampler: &Sampler,
+    params: MaterialUniform,
 ) -> Material {
-    let img = rgba.unwrap_or_else(|| image::RgbaImage::from_pixel(1, 1, image::Rgba([255,255,255,255])));
-    let tex = device.create_texture(&TextureDescriptor {
-        label: Some("baseColorTex"),
-        size: wgpu::Extent3d { width: img.width(), height: img.height(), depth_or_array_layers: 1 },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
-        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-        view_formats: &[],
+    let params_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("material_ubo"),
+        contents: bytemuck::bytes_of(&params),
+        usage: wgpu::BufferUsages::UNIFORM,
     });
-    let view = tex.create_view(&TextureViewDescriptor::default());
-    let sampler = device.create_sampler(&SamplerDescriptor::default());
+
     let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("material_bg"),
         layout: material_bgl,
         entries: &[
-            BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&view) },
-            BindGroupEntry { binding: 1, resource: BindingResource::Sampler(&sampler) },
+            BindGroupEntry { binding: 0, resource: BindingResource::TextureView(base_color) },
+            BindGroupEntry { binding: 1, resource: BindingResource::Sampler(sampler) },
+            BindGroupEntry { binding: 2, resource: params_buf.as_entire_binding() },
         ],
     });
+
     Material { bind_group: bg }
 }
@@ -0,0 +1,554 @@
+use crate::graphics::error::{LoadError, LoadWarning};
+
+pub const MESHOPT_EXTENSION: &str = "EXT_meshopt_compression";
+
+// A buffer that only exists to give compressed views somewhere to decode into. It has
+// no data of its own when it has no URI.
+pub fn is_placeholder(buffer: &gltf::Buffer) -> bool {
+    let fallback = buffer
+        .extension_value(MESHOPT_EXTENSION)
+        .and_then(|e| e.get("fallback")?.as_bool())
+        .unwrap_or(false);
+    fallback && matches!(buffer.source(), gltf::buffer::Source::Bin)
+}
+
+// Decompresses every buffer view carrying EXT_meshopt_compression into the range it
+// covers in its own buffer, so accessors read it like uncompressed data. A view that
+// cannot be decoded keeps the fallback buffer's data if that buffer has any.
+pub fn decode_buffer_views(
+    doc: &gltf::Document,
+    buffers: &mut [gltf::buffer::Data],
+    warnings: &mut Vec<LoadWarning>,
+) -> Result<(), LoadError> {
+    for view in doc.views() {
+        let Some(ext) = view.extension_value(MESHOPT_EXTENSION) else { continue };
+        let decoded = decode_view(ext, buffers).map_err(|reason| LoadError::BufferViewDecode { view: view.index(), reason });
+        let decoded = match decoded {
+            Ok(decoded) => decoded,
+            Err(cause) if !is_placeholder(&view.buffer()) => {
+                warnings.push(LoadWarning::CompressedViewFallback { view: view.index(), cause });
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let target = buffers
+            .get_mut(view.buffer().index())
+            .and_then(|b| b.0.get_mut(view.offset()..view.offset() + decoded.len()))
+            .filter(|_| decoded.len() <= view.length());
+        let Some(target) = target else {
+            let reason = format!("{} decoded bytes do not fit the view", decoded.len());
+            return Err(LoadError::BufferViewDecode { view: view.index(), reason });
+        };
+        target.copy_from_slice(&decoded);
+    }
+    Ok(())
+}
+
+fn decode_view(ext: &gltf::json::Value, buffers: &[gltf::buffer::Data]) -> Result<Vec<u8>, String> {
+    let field = |name: &str| ext.get(name).and_then(|v| v.as_u64()).map(|v| v as usize);
+    let required = |name: &str| field(name).ok_or_else(|| format!("{MESHOPT_EXTENSION} has no valid {name}"));
+    let (buffer, offset, length) = (required("buffer")?, field("byteOffset").unwrap_or(0), required("byteLength")?);
+    let (stride, count) = (required("byteStride")?, required("count")?);
+    let mode = ext.get("mode").and_then(|v| v.as_str()).unwrap_or_default();
+    let filter = ext.get("filter").and_then(|v| v.as_str()).unwrap_or("NONE");
+
+    let src = buffers
+        .get(buffer)
+        .and_then(|b| b.get(offset..offset.checked_add(length)?))
+        .ok_or_else(|| format!("compressed data is outside buffer {buffer}"))?;
+    let mut dst = vec![0u8; count.checked_mul(stride).ok_or("decoded size overflows")?];
+    match mode {
+        "ATTRIBUTES" => {
+            if stride == 0 || !stride.is_multiple_of(4) || stride > 256 {
+                return Err(format!("attribute stride {stride} is not a multiple of 4 up to 256"));
+            }
+            decode_vertex_buffer(&mut dst, stride, src)?;
+        }
+        "TRIANGLES" | "INDICES" => {
+            if stride != 2 && stride != 4 {
+                return Err(format!("index stride {stride} is not 2 or 4"));
+            }
+            let mut indices = vec![0u32; count];
+            if mode == "TRIANGLES" {
+                decode_index_buffer(&mut indices, src)?;
+            } else {
+                decode_index_sequence(&mut indices, src)?;
+            }
+            for (out, index) in dst.chunks_exact_mut(stride).zip(indices) {
+                out.copy_from_slice(&index.to_le_bytes()[..stride]);
+            }
+        }
+        other => return Err(format!("unknown mode {other:?}")),
+    }
+    match (mode, filter) {
+        (_, "NONE") => {}
+        ("ATTRIBUTES", "OCTAHEDRAL") if stride == 4 => filter_octahedral::<1>(&mut dst),
+        ("ATTRIBUTES", "OCTAHEDRAL") if stride == 8 => filter_octahedral::<2>(&mut dst),
+        ("ATTRIBUTES", "QUATERNION") if stride == 8 => filter_quaternion(&mut dst),
+        ("ATTRIBUTES", "EXPONENTIAL") => filter_exponential(&mut dst),
+        _ => return Err(format!("filter {filter} cannot be used with mode {mode} and stride {stride}")),
+    }
+    Ok(dst)
+}
+
+// Reads bytes front to back, failing instead of running past the end.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.data.get(self.pos).ok_or("compressed data is truncated")?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&[u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or("compressed data is truncated")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    // LEB128, at most five bytes.
+    fn vbyte(&mut self) -> Result<u32, String> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let b = self.byte()?;
+            result |= u32::from(b & 127) << shift;
+            if b < 128 {
+                break;
+            }
+        }
+        Ok(result)
+    }
+}
+
+const BYTE_GROUP_SIZE: usize = 16;
+const VERTEX_TAIL_MIN: usize = 32;
+
+// Attribute codec: vertices are split into blocks, each byte of the vertex is
+// delta-coded against the previous vertex and stored in groups of 16 with 0, 2, 4
+// or 8 bits per value.
+fn decode_vertex_buffer(dst: &mut [u8], stride: usize, src: &[u8]) -> Result<(), String> {
+    match src.first() {
+        Some(0xa0) => {}
+        Some(header) => return Err(format!("unsupported attribute codec header {header:#x}")),
+        None => return Err("compressed data is empty".to_owned()),
+    }
+    let tail = stride.max(VERTEX_TAIL_MIN);
+    if src.len() < 1 + tail {
+        return Err("compressed data is truncated".to_owned());
+    }
+    let mut last_vertex = src[src.len() - stride..].to_vec();
+    let block_size = ((8192 / stride) & !(BYTE_GROUP_SIZE - 1)).min(256);
+    let mut cursor = Cursor { data: &src[..src.len() - tail], pos: 1 };
+    let mut deltas = vec![0u8; block_size];
+    for block in dst.chunks_mut(block_size * stride) {
+        let vertex_count = block.len() / stride;
+        let aligned = vertex_count.next_multiple_of(BYTE_GROUP_SIZE);
+        for k in 0..stride {
+            decode_bytes(&mut cursor, &mut deltas[..aligned])?;
+            let mut prev = last_vertex[k];
+            for (i, &delta) in deltas[..vertex_count].iter().enumerate() {
+                prev = prev.wrapping_add((delta >> 1) ^ (delta & 1).wrapping_neg());
+                block[i * stride + k] = prev;
+            }
+        }
+        last_vertex.copy_from_slice(&block[block.len() - stride..]);
+    }
+    if cursor.pos != cursor.data.len() {
+        return Err("compressed data has trailing bytes".to_owned());
+    }
+    Ok(())
+}
+
+fn decode_bytes(cursor: &mut Cursor, out: &mut [u8]) -> Result<(), String> {
+    let groups = out.len() / BYTE_GROUP_SIZE;
+    let header = cursor.bytes(groups.div_ceil(4))?.to_vec();
+    for (g, group) in out.chunks_exact_mut(BYTE_GROUP_SIZE).enumerate() {
+        match (header[g / 4] >> ((g % 4) * 2)) & 3 {
+            0 => group.fill(0),
+            3 => group.copy_from_slice(cursor.bytes(BYTE_GROUP_SIZE)?),
+            bitslog2 => {
+                let bits = 1 << bitslog2;
+                let packed = cursor.bytes(BYTE_GROUP_SIZE * bits / 8)?.to_vec();
+                let max = (1u8 << bits) - 1;
+                for (i, out) in group.iter_mut().enumerate() {
+                    let shift = 8 - bits - (i * bits) % 8;
+                    let value = (packed[i * bits / 8] >> shift) & max;
+                    *out = if value == max { cursor.byte()? } else { value };
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Triangle codec: each triangle is a code byte that reuses an edge or vertex from
+// small FIFOs of recently seen ones, with explicit indices delta-coded in the data
+// stream. Version 1 adds codes for the last index plus or minus one.
+fn decode_index_buffer(dst: &mut [u32], src: &[u8]) -> Result<(), String> {
+    if !dst.len().is_multiple_of(3) {
+        return Err(format!("triangle index count {} is not a multiple of 3", dst.len()));
+    }
+    let version = match src.first() {
+        Some(&h) if h & 0xf0 == 0xe0 && h & 0x0f <= 1 => h & 0x0f,
+        Some(h) => return Err(format!("unsupported triangle codec header {h:#x}")),
+        None => return Err("compressed data is empty".to_owned()),
+    };
+    let triangles = dst.len() / 3;
+    if src.len() < 1 + triangles + 16 {
+        return Err("compressed data is truncated".to_owned());
+    }
+    let codes = &src[1..1 + triangles];
+    let (data, codeaux_table) = src.split_at(src.len() - 16);
+    let mut cursor = Cursor { data, pos: 1 + triangles };
+    let fecmax = if version >= 1 { 13 } else { 15 };
+
+    let mut fifo = Fifos::default();
+    let (mut next, mut last) = (0u32, 0u32);
+    let decode_index = |cursor: &mut Cursor, last: u32| -> Result<u32, String> {
+        let v = cursor.vbyte()?;
+        Ok(last.wrapping_add((v >> 1) ^ (v & 1).wrapping_neg()))
+    };
+
+    for (tri, &code) in dst.chunks_exact_mut(3).zip(codes) {
+        let (a, b, c);
+        if code < 0xf0 {
+            [a, b] = fifo.edge(1 + usize::from(code >> 4));
+            let fec = code & 15;
+            if fec < fecmax {
+                c = if fec == 0 { next } else { fifo.vertex(1 + usize::from(fec)) };
+                next += u32::from(fec == 0);
+                fifo.push_vertex(c, fec == 0);
+            } else {
+                c = match fec {
+                    13 => last.wrapping_sub(1),
+                    14 => last.wrapping_add(1),
+                    _ => decode_index(&mut cursor, last)?,
+                };
+                last = c;
+                fifo.push_vertex(c, true);
+            }
+        } else {
+            let (codeaux, fea) = if code < 0xfe {
+                (codeaux_table[usize::from(code & 15)], 0)
+            } else {
+                let codeaux = cursor.byte()?;
+                // An explicit all-zero code restarts numbering, e.g. between concatenated meshes.
+                if codeaux == 0 {
+                    next = 0;
+                }
+                (codeaux, if code == 0xfe { 0 } else { 15 })
+            };
+            let (feb, fec) = (usize::from(codeaux >> 4), usize::from(codeaux & 15));
+            let mut v = [0u32; 3];
+            for (out, fe) in v.iter_mut().zip([fea, feb, fec]) {
+                if fe == 0 {
+                    *out = next;
+                    next += 1;
+                } else {
+                    *out = fifo.vertex(fe);
+                }
+            }
+            for (out, fe) in v.iter_mut().zip([fea, feb, fec]) {
+                if fe == 15 {
+                    last = decode_index(&mut cursor, last)?;
+                    *out = last;
+                }
+            }
+            [a, b, c] = v;
+            fifo.push_vertex(a, true);
+            fifo.push_vertex(b, feb == 0 || feb == 15);
+            fifo.push_vertex(c, fec == 0 || fec == 15);
+            fifo.push_edge(b, a);
+        }
+        fifo.push_edge(c, b);
+        fifo.push_edge(a, c);
+        tri.copy_from_slice(&[a, b, c]);
+    }
+    if cursor.pos != cursor.data.len() {
+        return Err("compressed data has trailing bytes".to_owned());
+    }
+    Ok(())
+}
+
+// Recently used edges and vertices of the triangle codec, looked up counting back
+// from the most recent entry.
+struct Fifos {
+    edges: [[u32; 2]; 16],
+    edge_offset: usize,
+    vertices: [u32; 16],
+    vertex_offset: usize,
+}
+
+impl Default for Fifos {
+    fn default() -> Self {
+        Self { edges: [[u32::MAX; 2]; 16], edge_offset: 0, vertices: [u32::MAX; 16], vertex_offset: 0 }
+    }
+}
+
+impl Fifos {
+    fn edge(&self, back: usize) -> [u32; 2] {
+        self.edges[self.edge_offset.wrapping_sub(back) & 15]
+    }
+
+    fn vertex(&self, back: usize) -> u32 {
+        self.vertices[self.vertex_offset.wrapping_sub(back) & 15]
+    }
+
+    fn push_edge(&mut self, a: u32, b: u32) {
+        self.edges[self.edge_offset] = [a, b];
+        self.edge_offset = (self.edge_offset + 1) & 15;
+    }
+
+    // The slot is always written but only kept when `keep` is set.
+    fn push_vertex(&mut self, v: u32, keep: bool) {
+        self.vertices[self.vertex_offset] = v;
+        self.vertex_offset = (self.vertex_offset + usize::from(keep)) & 15;
+    }
+}
+
+// Index sequence codec: each index is a zigzag delta against one of two running
+// baselines, chosen by the low bit.
+fn decode_index_sequence(dst: &mut [u32], src: &[u8]) -> Result<(), String> {
+    match src.first() {
+        Some(&h) if h & 0xf0 == 0xd0 && h & 0x0f <= 1 => {}
+        Some(h) => return Err(format!("unsupported index codec header {h:#x}")),
+        None => return Err("compressed data is empty".to_owned()),
+    }
+    if src.len() < 1 + 4 {
+        return Err("compressed data is truncated".to_owned());
+    }
+    let mut cursor = Cursor { data: &src[..src.len() - 4], pos: 1 };
+    let mut last = [0u32; 2];
+    for out in dst {
+        let v = cursor.vbyte()?;
+        let current = (v & 1) as usize;
+        let v = v >> 1;
+        last[current] = last[current].wrapping_add((v >> 1) ^ (v & 1).wrapping_neg());
+        *out = last[current];
+    }
+    if cursor.pos != cursor.data.len() {
+        return Err("compressed data has trailing bytes".to_owned());
+    }
+    Ok(())
+}
+
+// Signed normalized components of `BYTES` bytes each, little endian.
+fn read_snorm<const BYTES: usize>(data: &[u8], i: usize) -> f32 {
+    match BYTES {
+        1 => data[i] as i8 as f32,
+        _ => i16::from_le_bytes([data[2 * i], data[2 * i + 1]]) as f32,
+    }
+}
+
+fn write_snorm<const BYTES: usize>(data: &mut [u8], i: usize, v: f32) {
+    let v = v.round() as i32;
+    match BYTES {
+        1 => data[i] = v as i8 as u8,
+        _ => data[2 * i..2 * i + 2].copy_from_slice(&(v as i16).to_le_bytes()),
+    }
+}
+
+// Unit vectors stored as octahedral x and y, with z holding the encoding's 1.0.
+fn filter_octahedral<const BYTES: usize>(data: &mut [u8]) {
+    let max = ((1 << (BYTES * 8 - 1)) - 1) as f32;
+    for v in data.chunks_exact_mut(4 * BYTES) {
+        let (mut x, mut y) = (read_snorm::<BYTES>(v, 0), read_snorm::<BYTES>(v, 1));
+        let z = read_snorm::<BYTES>(v, 2) - x.abs() - y.abs();
+        let t = z.min(0.0);
+        x += if x >= 0.0 { t } else { -t };
+        y += if y >= 0.0 { t } else { -t };
+        let s = max / (x * x + y * y + z * z).sqrt();
+        write_snorm::<BYTES>(v, 0, x * s);
+        write_snorm::<BYTES>(v, 1, y * s);
+        write_snorm::<BYTES>(v, 2, z * s);
+    }
+}
+
+// Unit quaternions stored as three components scaled by sqrt(2); the fourth, largest
+// one is rebuilt, and the low two bits of the last value say where it goes.
+fn filter_quaternion(data: &mut [u8]) {
+    for v in data.chunks_exact_mut(8) {
+        let last = i16::from_le_bytes([v[6], v[7]]);
+        let scale = std::f32::consts::FRAC_1_SQRT_2 / f32::from(last | 3);
+        let [x, y, z] = [0, 1, 2].map(|i| read_snorm::<2>(v, i) * scale);
+        let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+        let qc = (last & 3) as usize;
+        for (i, c) in [w, x, y, z].into_iter().enumerate() {
+            write_snorm::<2>(v, (qc + i) & 3, c * 32767.0);
+        }
+    }
+}
+
+// Floats stored as a signed 24-bit mantissa and signed 8-bit exponent.
+fn filter_exponential(data: &mut [u8]) {
+    for v in data.chunks_exact_mut(4) {
+        let bits = i32::from_le_bytes([v[0], v[1], v[2], v[3]]);
+        let (mantissa, exponent) = ((bits << 8) >> 8, bits >> 24);
+        let value = mantissa as f32 * f32::from_bits(((exponent + 127) as u32) << 23);
+        v.copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+// Reference streams are meshoptimizer's own test vectors, or the output of its encoder
+// where upstream has none.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Three u16 position components, an octahedral i8 normal and two u16 texture coordinates.
+    const VERTEX_BUFFER: [[u16; 6]; 4] = [
+        [0, 0, 0, 0, 0, 0],
+        [300, 0, 0, 0, 500, 0],
+        [0, 300, 0, 0, 0, 500],
+        [300, 300, 0, 0, 500, 500],
+    ];
+
+    const VERTEX_DATA_V0: [u8; 85] = [
+        0xa0, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x58, 0x57, 0x58, 0x01, 0x26, 0x00, 0x00, 0x00, 0x01, 0x0c, 0x00, 0x00,
+        0x00, 0x58, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x3f, 0x00, 0x00, 0x00, 0x17, 0x18,
+        0x17, 0x01, 0x26, 0x00, 0x00, 0x00, 0x01, 0x0c, 0x00, 0x00, 0x00, 0x17, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    // 0, 2, 4 and 8 bit groups in one stream, with vertices 7 and 13 set to 42 to force sentinels.
+    const VERTEX_DATA_SENTINELS: [u8; 81] = [
+        0xa0, 0x01, 0x00, 0x03, 0xc0, 0x3c, 0x54, 0x53, 0x54, 0x53, 0x01, 0x2a, 0xab, 0xea, 0xbe, 0x48, 0x43, 0x3c,
+        0x37, 0x02, 0x04, 0x44, 0x44, 0x4f, 0xf4, 0x44, 0x4f, 0xf4, 0x3c, 0x33, 0x24, 0x1b, 0x03, 0x00, 0x10, 0x10,
+        0x10, 0x10, 0x10, 0x10, 0x0b, 0x2c, 0x10, 0x10, 0x10, 0x10, 0x6b, 0x8c, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    // The 4 6 5 triangle cannot use the next index, so the one after it cannot either.
+    const INDEX_BUFFER: [u32; 12] = [0, 1, 2, 2, 1, 3, 4, 6, 5, 7, 8, 9];
+
+    const INDEX_DATA_V0: [u8; 27] = [
+        0xe0, 0xf0, 0x10, 0xfe, 0xff, 0xf0, 0x0c, 0xff, 0x02, 0x02, 0x02, 0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9,
+        0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00,
+    ];
+
+    // Exercises the restart of 0 1 2 and the last index codes of version 1.
+    const INDEX_BUFFER_TRICKY: [u32; 15] = [0, 1, 2, 2, 1, 3, 0, 1, 2, 2, 1, 5, 2, 1, 4];
+
+    const INDEX_DATA_V1: [u8; 24] = [
+        0xe1, 0xf0, 0x10, 0xfe, 0x1f, 0x3d, 0x00, 0x0a, 0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9, 0x86, 0x65, 0x89,
+        0x68, 0x98, 0x01, 0x69, 0x00, 0x00,
+    ];
+
+    const INDEX_SEQUENCE: [u32; 6] = [0, 1, 51, 2, 49, 1000];
+
+    const INDEX_SEQUENCE_V0: [u8; 13] = [0xd0, 0x00, 0x04, 0xcd, 0x01, 0x04, 0x07, 0x98, 0x1f, 0x00, 0x00, 0x00, 0x00];
+
+    const INDEX_SEQUENCE_V1: [u8; 13] = [0xd1, 0x00, 0x04, 0xcd, 0x01, 0x04, 0x07, 0x98, 0x1f, 0x00, 0x00, 0x00, 0x00];
+
+    fn vertex_bytes() -> Vec<u8> {
+        VERTEX_BUFFER.iter().flatten().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn u16_bytes<const N: usize>(values: [[u16; 4]; N]) -> Vec<u8> {
+        values.iter().flatten().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    // Every shorter prefix of a valid stream must be rejected, and so must one more byte.
+    fn check_bounds<T>(src: &[u8], decode: impl Fn(&[u8]) -> Result<T, String>) {
+        for len in 0..src.len() {
+            assert!(decode(&src[..len]).is_err(), "prefix of {len} bytes was accepted");
+        }
+        let mut extended = src.to_vec();
+        extended.push(0);
+        assert!(decode(&extended).is_err(), "trailing byte was accepted");
+    }
+
+    #[test]
+    fn vertex_buffer_v0() {
+        let mut dst = vec![0u8; 48];
+        decode_vertex_buffer(&mut dst, 12, &VERTEX_DATA_V0).unwrap();
+        assert_eq!(dst, vertex_bytes());
+        check_bounds(&VERTEX_DATA_V0, |src| decode_vertex_buffer(&mut [0u8; 48], 12, src));
+    }
+
+    #[test]
+    fn vertex_buffer_bit_groups() {
+        let expected: Vec<u8> = (0..16u8).flat_map(|i| if i == 7 || i == 13 { [42; 4] } else { [0, i, i * 2, i * 8] }).collect();
+        let mut dst = vec![0u8; 64];
+        decode_vertex_buffer(&mut dst, 4, &VERTEX_DATA_SENTINELS).unwrap();
+        assert_eq!(dst, expected);
+        check_bounds(&VERTEX_DATA_SENTINELS, |src| decode_vertex_buffer(&mut [0u8; 64], 4, src));
+    }
+
+    #[test]
+    fn vertex_buffer_malformed_header() {
+        let mut src = VERTEX_DATA_V0;
+        src[0] = 0;
+        assert!(decode_vertex_buffer(&mut [0u8; 48], 12, &src).is_err());
+    }
+
+    #[test]
+    fn index_buffer_v0() {
+        let mut dst = [0u32; 12];
+        decode_index_buffer(&mut dst, &INDEX_DATA_V0).unwrap();
+        assert_eq!(dst, INDEX_BUFFER);
+        check_bounds(&INDEX_DATA_V0, |src| decode_index_buffer(&mut [0u32; 12], src));
+    }
+
+    #[test]
+    fn index_buffer_v1() {
+        let mut dst = [0u32; 15];
+        decode_index_buffer(&mut dst, &INDEX_DATA_V1).unwrap();
+        assert_eq!(dst, INDEX_BUFFER_TRICKY);
+        check_bounds(&INDEX_DATA_V1, |src| decode_index_buffer(&mut [0u32; 15], src));
+    }
+
+    #[test]
+    fn index_buffer_malformed_header() {
+        let mut src = INDEX_DATA_V1;
+        src[0] = 0xe2;
+        assert!(decode_index_buffer(&mut [0u32; 15], &src).is_err());
+    }
+
+    #[test]
+    fn index_sequence() {
+        for src in [INDEX_SEQUENCE_V0, INDEX_SEQUENCE_V1] {
+            let mut dst = [0u32; 6];
+            decode_index_sequence(&mut dst, &src).unwrap();
+            assert_eq!(dst, INDEX_SEQUENCE);
+            check_bounds(&src, |src| decode_index_sequence(&mut [0u32; 6], src));
+        }
+    }
+
+    #[test]
+    fn filter_octahedral_8() {
+        let mut data = [[0, 1, 127, 0], [0, 187, 127, 1], [255, 1, 127, 0], [14, 130, 127, 1]].concat();
+        filter_octahedral::<1>(&mut data);
+        assert_eq!(data, [[0, 1, 127, 0], [0, 159, 82, 1], [255, 1, 127, 0], [1, 130, 241, 1]].concat());
+    }
+
+    #[test]
+    fn filter_octahedral_12() {
+        let mut data = u16_bytes([[0, 1, 2047, 0], [0, 1870, 2047, 1], [2017, 1, 2047, 0], [14, 1300, 2047, 1]]);
+        filter_octahedral::<2>(&mut data);
+        let expected = u16_bytes([[0, 16, 32767, 0], [0, 32621, 3088, 1], [32764, 16, 471, 0], [307, 28541, 16093, 1]]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn filter_quaternion_12() {
+        let mut data = u16_bytes([[0, 1, 0, 0x7fc], [0, 1870, 0, 0x7fd], [2017, 1, 0, 0x7fe], [14, 1300, 0, 0x7ff]]);
+        filter_quaternion(&mut data);
+        let expected = u16_bytes([[32767, 0, 11, 0], [0, 25013, 0, 21166], [11, 0, 23504, 22830], [158, 14715, 0, 29277]]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn filter_exponential_32() {
+        let mut data: Vec<u8> = [0u32, 0xff000003, 0x02fffff7, 0xfe7fffff].iter().flat_map(|v| v.to_le_bytes()).collect();
+        filter_exponential(&mut data);
+        let expected: Vec<u8> = [0u32, 0x3fc00000, 0xc2100000, 0x49fffffe].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(data, expected);
+    }
+}
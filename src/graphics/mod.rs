@@ -3,18 +3,31 @@ mod depth;
 mod camera;
 mod model;
 mod loader;
+mod error;
+mod geometry;
+mod resolver;
+mod watch;
+mod scene;
+mod texture;
+mod ktx;
+mod meshopt;
+mod draco;
+mod animation;
 
-use std::path::Path;
+use std::{collections::HashMap, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use notify::RecommendedWatcher;
 
 use winit::{
     dpi::PhysicalSize,
-    event::{WindowEvent, MouseButton, ElementState, MouseScrollDelta, DeviceEvent},
+    event::{WindowEvent, MouseButton, ElementState, MouseScrollDelta, DeviceEvent, KeyEvent},
     event_loop::EventLoopProxy,
+    keyboard::{KeyCode, PhysicalKey},
     window::Window,
 };
 
 use wgpu::{
-    Adapter, BindGroup, Buffer, Color, CommandEncoderDescriptor, Device, ExperimentalFeatures, Features, Instance, Limits, LoadOp, MemoryHints, Operations, PowerPreference, Queue, RenderPassColorAttachment,
+    Adapter, BindGroup, Buffer, Color, CommandEncoderDescriptor, Device, ExperimentalFeatures, FrontFace, Instance, Limits, LoadOp, MemoryHints, Operations, PowerPreference, PrimitiveTopology, Queue, RenderPassColorAttachment,
     RenderPassDescriptor, RenderPipeline, RequestAdapterOptions, StoreOp, Surface,
     SurfaceConfiguration, Texture, TextureView, TextureViewDescriptor,
 };
@@ -23,11 +36,21 @@ pub type Rc<T> = std::sync::Arc<T>;
 
 use camera::{make_camera, update_camera_buffer};
 use depth::create_depth;
-use loader::load_gltf_model;
-use model::{create_model_ubo, Model};
-use pipeline::{create_bind_group_layouts, create_pipeline};
+use error::{LoadError, LoadWarning};
+use loader::{load_gltf_model, LoadProgress};
+
+pub use geometry::NormalMode;
+pub use loader::LoadOptions;
+use model::Model;
+use pipeline::{create_bind_group_layouts, create_pipeline, Layouts};
+use watch::watch_sources;
 
-pub async fn create_graphics(window: Rc<Window>, proxy: EventLoopProxy<Graphics>) {
+pub async fn create_graphics(
+    window: Rc<Window>,
+    proxy: EventLoopProxy<GraphicsEvent>,
+    load_options: LoadOptions,
+    animation: Option<String>,
+) {
     let instance = Instance::default();
     let surface = instance.create_surface(std::sync::Arc::clone(&window)).unwrap();
     let adapter = instance
@@ -43,8 +66,9 @@ pub async fn create_graphics(window: Rc<Window>, proxy: EventLoopProxy<Graphics>
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: Features::empty(),
-                required_limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+                required_features: adapter.features() & (ktx::COMPRESSION_FEATURES | texture::PRECISION_FEATURES),
+                // Morph targets and skins are read from storage buffers in the vertex shader.
+                required_limits: Limits::downlevel_defaults().using_resolution(adapter.limits()),
                 memory_hints: MemoryHints::Performance,
                 trace: Default::default(),
                 experimental_features: ExperimentalFeatures::disabled(),
@@ -60,22 +84,11 @@ pub async fn create_graphics(window: Rc<Window>, proxy: EventLoopProxy<Graphics>
     surface.configure(&device, &surface_config);
     let (depth_view, depth_tex) = create_depth(&device, surface_config.width, surface_config.height);
     let layouts = create_bind_group_layouts(&device);
-    let (render_pipeline, camera_bg, camera_buf, model_bgl) =
+    let (pipelines, camera_bg, camera_buf) =
         create_pipeline(&device, surface_config.format, &layouts);
     let cam = make_camera(surface_config.width, surface_config.height);
     queue.write_buffer(&camera_buf, 0, bytemuck::cast_slice(&[cam.view_proj.to_cols_array()]));
 
-    let model = load_gltf_model(
-        &device,
-        &queue,
-        &layouts.material_bgl,
-        Path::new("assets/BoomBox.glb"),
-    )
-    .await
-    .expect("Failed to load glTF");
-
-    let (model_buf, model_bg) = create_model_ubo(&device, &model_bgl, model.recommended_xform);
-
     let yaw = 0.6_f32;
     let pitch = 0.5_f32;
     let radius = 3.0_f32;
@@ -83,55 +96,86 @@ pub async fn create_graphics(window: Rc<Window>, proxy: EventLoopProxy<Graphics>
 
     let gfx = Graphics {
         window: window.clone(),
+        proxy: proxy.clone(),
         instance,
         surface,
         surface_config,
         adapter,
         device,
         queue,
-        render_pipeline,
+        layouts,
+        pipelines,
         depth_view,
         _depth_tex: depth_tex,
         camera_bg,
         camera_buf,
-        model_bg,
-        model_buf,
-        model,
+        model: Model::empty(),
+        load_options,
+        animation,
+        load_generation: 0,
+        watcher: None,
+        reload_at: None,
         yaw,
         pitch,
         radius,
         target,
         rotating: false,
         last_cursor: glam::vec2(0.0, 0.0),
+        last_frame: Instant::now(),
     };
 
-    let _ = proxy.send_event(gfx);
+    let _ = proxy.send_event(GraphicsEvent::Ready(Box::new(gfx)));
+}
+
+pub const WINDOW_TITLE: &str = "WGPU GLTF";
+const RELOAD_DELAY: Duration = Duration::from_millis(200);
+
+pub enum GraphicsEvent {
+    Ready(Box<Graphics>),
+    LoadProgress {
+        generation: u64,
+        path: PathBuf,
+        progress: LoadProgress,
+    },
+    ModelLoaded {
+        generation: u64,
+        path: PathBuf,
+        result: Box<Result<(Model, Vec<LoadWarning>), LoadError>>,
+    },
+    SourceChanged,
 }
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct Graphics {
     pub(crate) window: Rc<Window>,
+    proxy: EventLoopProxy<GraphicsEvent>,
     instance: Instance,
     surface: Surface<'static>,
     surface_config: SurfaceConfiguration,
     adapter: Adapter,
     device: Device,
     queue: Queue,
-    render_pipeline: RenderPipeline,
+    layouts: Layouts,
+    pipelines: HashMap<(PrimitiveTopology, FrontFace), RenderPipeline>,
     depth_view: TextureView,
     _depth_tex: Texture,
     camera_bg: BindGroup,
     camera_buf: Buffer,
-    model_bg: BindGroup,
-    model_buf: Buffer,
     model: Model,
+    load_options: LoadOptions,
+    // Played instead of the first animation of each loaded model.
+    animation: Option<String>,
+    load_generation: u64,
+    watcher: Option<RecommendedWatcher>,
+    reload_at: Option<Instant>,
     yaw: f32,
     pitch: f32,
     radius: f32,
     target: glam::Vec3,
     rotating: bool,
     last_cursor: glam::Vec2,
+    last_frame: Instant,
 }
 
 impl Graphics {
@@ -139,6 +183,79 @@ impl Graphics {
         self.window.request_redraw();
     }
 
+    // Parses, decodes and uploads on a worker thread; the current model stays on
+    // screen until the result arrives as a `GraphicsEvent::ModelLoaded`.
+    pub fn load_model(&mut self, path: &Path) {
+        self.load_generation += 1;
+        let generation = self.load_generation;
+        let path = path.to_path_buf();
+        let (device, queue, layouts) = (self.device.clone(), self.queue.clone(), self.layouts.clone());
+        let proxy = self.proxy.clone();
+        let options = self.load_options;
+        std::thread::spawn(move || {
+            let mut on_progress = |progress| {
+                let _ = proxy.send_event(GraphicsEvent::LoadProgress { generation, path: path.clone(), progress });
+            };
+            let result = pollster::block_on(load_gltf_model(&device, &queue, &layouts, &path, &options, &mut on_progress));
+            let _ = proxy.send_event(GraphicsEvent::ModelLoaded { generation, path, result: Box::new(result) });
+        });
+    }
+
+    // Results of superseded loads are dropped.
+    pub fn handle_graphics_event(&mut self, event: GraphicsEvent) {
+        match event {
+            GraphicsEvent::LoadProgress { generation, path, progress } if generation == self.load_generation => {
+                self.window.set_title(&format!(
+                    "{WINDOW_TITLE} - loading {} ({} KiB, {}/{} images, {}/{} meshes)",
+                    path.display(),
+                    progress.bytes_parsed / 1024,
+                    progress.images_decoded,
+                    progress.images_total,
+                    progress.meshes_uploaded,
+                    progress.meshes_total,
+                ));
+            }
+            GraphicsEvent::ModelLoaded { generation, path, result } if generation == self.load_generation => {
+                match *result {
+                    Ok((mut model, warnings)) => {
+                        for w in &warnings {
+                            log::warn!("{}: {w}", path.display());
+                        }
+                        // A reload of the same file keeps the scene being looked at and
+                        // the animation being played.
+                        let reload = model.sources.first() == self.model.sources.first();
+                        if reload && let Some(active) = self.model.graph.active_scene() {
+                            model.set_active_scene(&self.queue, active);
+                        }
+                        let animation = match self.model.animations.current() {
+                            Some(current) if reload => current.name.clone(),
+                            _ => self.animation.clone(),
+                        };
+                        if let Some(name) = animation
+                            && !model.select_animation(&name)
+                        {
+                            log::warn!("{}: no animation named {name}", path.display());
+                        }
+                        self.watcher = watch_sources(&model.sources, self.proxy.clone())
+                            .map_err(|e| log::warn!("Cannot watch {}: {e}", path.display()))
+                            .ok();
+                        self.model = model;
+                        self.window.set_title(&format!("{WINDOW_TITLE} - {}", path.display()));
+                    }
+                    Err(e) => {
+                        log::error!("Failed to load {}: {e}", path.display());
+                        self.window.set_title(&format!("{WINDOW_TITLE} - failed to load {}", path.display()));
+                    }
+                }
+            }
+            // Saves usually arrive as a burst of events; wait for them to settle.
+            GraphicsEvent::SourceChanged => {
+                self.reload_at = Some(Instant::now() + RELOAD_DELAY);
+            }
+            _ => {}
+        }
+    }
+
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.surface_config.width = new_size.width.max(1);
         self.surface_config.height = new_size.height.max(1);
@@ -151,6 +268,17 @@ impl Graphics {
     }
 
     pub fn draw(&mut self) {
+        if self.reload_at.is_some_and(|t| Instant::now() >= t) {
+            self.reload_at = None;
+            if let Some(path) = self.model.sources.first().cloned() {
+                self.load_model(&path);
+            }
+        }
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        self.model.animate(dt);
+        self.model.update_transforms(&self.queue);
         update_camera_buffer(&self.queue, &self.camera_buf, self.surface_config.width, self.surface_config.height,
                              self.yaw, self.pitch, self.radius, self.target);
 
@@ -186,11 +314,11 @@ impl Graphics {
                 occlusion_query_set: None,
             });
 
-            r_pass.set_pipeline(&self.render_pipeline);
             r_pass.set_bind_group(0, &self.camera_bg, &[]);
-            r_pass.set_bind_group(1, &self.model_bg, &[]);
 
-            for mesh in &self.model.meshes {
+            for mesh in self.model.visible_meshes().filter(|m| m.index_count > 0) {
+                r_pass.set_pipeline(&self.pipelines[&(mesh.topology, self.model.front_face(mesh))]);
+                r_pass.set_bind_group(1, &mesh.model_bg, &[]);
                 let mat = &self.model.materials[mesh.material_id.min(self.model.materials.len() - 1)];
                 r_pass.set_bind_group(2, &mat.bind_group, &[]);
 
@@ -206,10 +334,8 @@ impl Graphics {
 
     pub fn handle_window_event(&mut self, event: &WindowEvent) {
         match event {
-            WindowEvent::MouseInput { state, button, .. } => {
-                if *button == MouseButton::Left {
-                    self.rotating = *state == ElementState::Pressed;
-                }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.rotating = *state == ElementState::Pressed;
             }
             WindowEvent::CursorMoved { position, .. } => {
                 let pos = glam::vec2(position.x as f32, position.y as f32);
@@ -227,16 +353,31 @@ impl Graphics {
                 };
                 self.radius = (self.radius + d).clamp(0.5, 50.0);
             }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(KeyCode::Tab), state: ElementState::Pressed, repeat: false, .. },
+                ..
+            } => {
+                self.model.cycle_scene(&self.queue);
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(key), state: ElementState::Pressed, repeat: false, .. },
+                ..
+            } => match key {
+                KeyCode::Space => self.model.animations.toggle_playing(),
+                KeyCode::KeyN => self.model.cycle_animation(),
+                KeyCode::KeyL => self.model.animations.looping ^= true,
+                _ => {}
+            },
             _ => {}
         }
     }
 
     pub fn handle_device_event(&mut self, event: &DeviceEvent) {
-        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
-            if self.rotating {
-                self.yaw   -= (*dx as f32) * 0.0025;
-                self.pitch -= (*dy as f32) * 0.0025;
-            }
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event
+            && self.rotating
+        {
+            self.yaw   -= (*dx as f32) * 0.0025;
+            self.pitch -= (*dy as f32) * 0.0025;
         }
     }
 }
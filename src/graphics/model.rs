@@ -1,6 +1,11 @@
+use std::path::PathBuf;
+
 use bytemuck::{Pod, Zeroable};
 use glam::Mat4;
-use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, VertexAttribute, VertexBufferLayout};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Queue, VertexAttribute, VertexBufferLayout};
+
+use crate::graphics::animation::AnimationPlayer;
+use crate::graphics::scene::{SceneGraph, Skin};
 
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
@@ -8,13 +13,19 @@ pub struct Vertex {
     pub pos: [f32; 3],
     pub nrm: [f32; 3],
     pub uv: [f32; 2],
+    pub tan: [f32; 4],
+    pub uv1: [f32; 2],
+    pub color: [f32; 4],
 }
 impl Vertex {
     pub fn layout() -> VertexBufferLayout<'static> {
         const ATTRS: &[VertexAttribute] = &wgpu::vertex_attr_array![
             0 => Float32x3, // pos
             1 => Float32x3, // nrm
-            2 => Float32x2  // uv
+            2 => Float32x2, // uv
+            3 => Float32x4, // tan
+            4 => Float32x2, // uv1
+            5 => Float32x4  // color
         ];
         VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as u64,
@@ -24,12 +35,40 @@ impl Vertex {
     }
 }
 
+// Mirrors `Influence` in shader.wgsl: one JOINTS_n/WEIGHTS_n set of a vertex.
+#[repr(C)]
+#[derive(Copy, Clone, Default, Pod, Zeroable)]
+pub struct SkinInfluence {
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
 #[derive(Debug)]
 pub struct GpuMesh {
     pub vbuf: wgpu::Buffer,
     pub ibuf: wgpu::Buffer,
     pub index_count: u32,
     pub material_id: usize,
+    pub topology: wgpu::PrimitiveTopology,
+    pub node: usize,
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+    pub model_buf: Buffer,
+    pub model_bg: BindGroup,
+    // Mirrors `MorphWeights` in shader.wgsl: target and vertex count, then one weight per target.
+    pub weights_buf: Buffer,
+    pub target_count: usize,
+    // Mirrors `Skin` in shader.wgsl: influence sets per vertex, then the joint matrices.
+    pub joints_buf: Buffer,
+    pub skin: Option<usize>,
+}
+
+// Mirrors `MaterialParams` in shader.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Default, Pod, Zeroable)]
+pub struct MaterialUniform {
+    pub base_color_texcoord: u32,
+    pub _pad: [u32; 3],
 }
 
 #[derive(Debug)]
@@ -41,10 +80,212 @@ pub struct Material {
 pub struct Model {
     pub meshes: Vec<GpuMesh>,
     pub materials: Vec<Material>,
+    pub graph: SceneGraph,
+    pub skins: Vec<Skin>,
+    pub animations: AnimationPlayer,
+    pub visible: Vec<usize>,
     pub recommended_xform: glam::Mat4,
+    // Files the model was read from, empty when loaded from memory.
+    pub sources: Vec<PathBuf>,
+}
+
+impl Model {
+    pub fn empty() -> Self {
+        Self {
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            graph: SceneGraph::default(),
+            skins: Vec::new(),
+            animations: AnimationPlayer::default(),
+            visible: Vec::new(),
+            recommended_xform: Mat4::IDENTITY,
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn visible_meshes(&self) -> impl Iterator<Item = &GpuMesh> {
+        self.visible.iter().map(|&ix| &self.meshes[ix])
+    }
+
+    pub fn set_active_scene(&mut self, queue: &Queue, ix: usize) -> bool {
+        if !self.graph.set_active_scene(ix) {
+            return false;
+        }
+        self.refresh_active_scene(queue);
+        true
+    }
+
+    pub fn cycle_scene(&mut self, queue: &Queue) {
+        let count = self.graph.scenes.len();
+        if count > 1 {
+            let next = self.graph.active_scene().map_or(0, |ix| (ix + 1) % count);
+            self.set_active_scene(queue, next);
+        }
+    }
+
+    pub fn animate(&mut self, dt: f32) {
+        self.animations.advance(dt, &mut self.graph);
+    }
+
+    pub fn select_animation(&mut self, name: &str) -> bool {
+        self.animations.select_by_name(name, &mut self.graph)
+    }
+
+    pub fn cycle_animation(&mut self) {
+        self.animations.cycle(&mut self.graph);
+    }
+
+    pub(crate) fn refresh_active_scene(&mut self, queue: &Queue) {
+        let mut in_scene = vec![false; self.graph.nodes().len()];
+        for ix in self.graph.active_nodes() {
+            in_scene[ix] = true;
+        }
+        self.visible = (0..self.meshes.len()).filter(|&i| in_scene[self.meshes[i].node]).collect();
+        self.recommended_xform = self.fit_xform();
+        self.write_transforms(queue);
+    }
+
+    // Scale and center everything that is drawn into a 2x2x2 box around the origin.
+    pub fn fit_xform(&self) -> Mat4 {
+        let mut min_v = glam::vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max_v = glam::vec3(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for mesh in self.visible_meshes() {
+            // Skinned vertices are placed by their joints, not the mesh node.
+            let world = if mesh.skin.is_some() { Mat4::IDENTITY } else { self.graph.nodes()[mesh.node].world() };
+            for corner in aabb_corners(mesh.min, mesh.max) {
+                let p = world.transform_point3(corner);
+                min_v = min_v.min(p);
+                max_v = max_v.max(p);
+            }
+        }
+        if self.visible.is_empty() {
+            return Mat4::IDENTITY;
+        }
+
+        let center = (min_v + max_v) * 0.5;
+        let extent = max_v - min_v;
+        let max_dim = extent.max_element().max(1e-5);
+        let scale   = 1.0 / max_dim;
+        Mat4::from_scale(glam::Vec3::splat(scale * 2.0)) * Mat4::from_translation(-center)
+    }
+
+    pub fn write_transforms(&self, queue: &Queue) {
+        for mesh in &self.meshes {
+            self.write_mesh_transform(queue, mesh);
+        }
+    }
+
+    // Pushes world matrices, joint matrices and morph weights of nodes touched since
+    // the last call to their buffers.
+    pub fn update_transforms(&mut self, queue: &Queue) {
+        let changed = self.graph.update_world_transforms();
+        let mut touched = vec![false; self.graph.nodes().len()];
+        for ix in changed {
+            touched[ix] = true;
+        }
+        for mesh in self.meshes.iter().filter(|m| m.skin.is_none() && touched[m.node]) {
+            self.write_mesh_transform(queue, mesh);
+        }
+        let posed: Vec<bool> = self.skins.iter().map(|s| s.joints.iter().any(|&j| touched[j])).collect();
+        for mesh in self.meshes.iter().filter(|m| m.skin.is_some_and(|s| posed[s])) {
+            self.write_joint_matrices(queue, mesh);
+        }
+
+        let changed = self.graph.take_changed_weights();
+        if changed.is_empty() {
+            return;
+        }
+        touched.fill(false);
+        for ix in changed {
+            touched[ix] = true;
+        }
+        for mesh in self.meshes.iter().filter(|m| touched[m.node] && m.target_count > 0) {
+            let weights = morph_weights(self.graph.nodes()[mesh.node].weights(), mesh.target_count);
+            queue.write_buffer(&mesh.weights_buf, MORPH_WEIGHTS_OFFSET, bytemuck::cast_slice(&weights));
+        }
+    }
+
+    // A node transform with a negative determinant mirrors the mesh, which reverses
+    // its winding. Skinned meshes ignore their node transform.
+    pub fn front_face(&self, mesh: &GpuMesh) -> wgpu::FrontFace {
+        if mesh.skin.is_none() && self.graph.nodes()[mesh.node].world().determinant() < 0.0 {
+            wgpu::FrontFace::Cw
+        } else {
+            wgpu::FrontFace::Ccw
+        }
+    }
+
+    fn write_mesh_transform(&self, queue: &Queue, mesh: &GpuMesh) {
+        let node = if mesh.skin.is_some() { Mat4::IDENTITY } else { self.graph.nodes()[mesh.node].world() };
+        let xform = self.recommended_xform * node;
+        queue.write_buffer(&mesh.model_buf, 0, bytemuck::cast_slice(&[xform.to_cols_array()]));
+    }
+
+    fn write_joint_matrices(&self, queue: &Queue, mesh: &GpuMesh) {
+        let Some(skin) = mesh.skin.and_then(|s| self.skins.get(s)) else { return };
+        let matrices: Vec<[f32; 16]> = skin.joint_matrices(&self.graph).iter().map(Mat4::to_cols_array).collect();
+        queue.write_buffer(&mesh.joints_buf, JOINT_MATRICES_OFFSET, bytemuck::cast_slice(&matrices));
+    }
+}
+
+// Joint matrices start after the 16-byte header of `Skin`.
+const JOINT_MATRICES_OFFSET: u64 = 16;
+
+pub fn create_joints_buffer(device: &wgpu::Device, influence_sets: u32, joint_matrices: &[Mat4]) -> Buffer {
+    let mut contents = bytemuck::bytes_of(&[influence_sets, 0, 0, 0]).to_vec();
+    for m in joint_matrices {
+        contents.extend_from_slice(bytemuck::bytes_of(&m.to_cols_array()));
+    }
+    // The binding needs room for at least one matrix even without a skin.
+    if joint_matrices.is_empty() {
+        contents.extend_from_slice(bytemuck::bytes_of(&Mat4::IDENTITY.to_cols_array()));
+    }
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("skin_joints"),
+        contents: &contents,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+// Weights start after the two counts of `MorphWeights`.
+const MORPH_WEIGHTS_OFFSET: u64 = 8;
+
+// Missing weights are zero and extra ones are ignored.
+fn morph_weights(weights: &[f32], target_count: usize) -> Vec<f32> {
+    (0..target_count).map(|t| weights.get(t).copied().unwrap_or(0.0)).collect()
+}
+
+pub fn create_weights_buffer(device: &wgpu::Device, target_count: usize, vertex_count: usize, weights: &[f32]) -> Buffer {
+    let mut contents = vec![target_count as u32, vertex_count as u32];
+    contents.extend(morph_weights(weights, target_count.max(1)).iter().map(|w| w.to_bits()));
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("morph_weights"),
+        contents: bytemuck::cast_slice(&contents),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn aabb_corners(min: glam::Vec3, max: glam::Vec3) -> [glam::Vec3; 8] {
+    [
+        glam::vec3(min.x, min.y, min.z),
+        glam::vec3(max.x, min.y, min.z),
+        glam::vec3(min.x, max.y, min.z),
+        glam::vec3(max.x, max.y, min.z),
+        glam::vec3(min.x, min.y, max.z),
+        glam::vec3(max.x, min.y, max.z),
+        glam::vec3(min.x, max.y, max.z),
+        glam::vec3(max.x, max.y, max.z),
+    ]
 }
 
-pub fn create_model_ubo(device: &wgpu::Device, layout: &BindGroupLayout, model: Mat4) -> (Buffer, BindGroup) {
+// Per-instance bindings: the world matrix, then the storage buffers behind
+// `morph_targets`, `morph`, `skin_influences` and `skin` in shader.wgsl.
+pub fn create_model_ubo(
+    device: &wgpu::Device,
+    layout: &BindGroupLayout,
+    model: Mat4,
+    storage: [&Buffer; 4],
+) -> (Buffer, BindGroup) {
     let buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("model_ubo"),
         contents: bytemuck::cast_slice(&[model.to_cols_array()]),
@@ -53,7 +294,11 @@ pub fn create_model_ubo(device: &wgpu::Device, layout: &BindGroupLayout, model:
     let bg = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("model_bg"),
         layout,
-        entries: &[wgpu::BindGroupEntry { binding: 0, resource: buf.as_entire_binding() }],
+        entries: &std::iter::once(&buf)
+            .chain(storage)
+            .enumerate()
+            .map(|(binding, b)| wgpu::BindGroupEntry { binding: binding as u32, resource: b.as_entire_binding() })
+            .collect::<Vec<_>>(),
     });
     (buf, bg)
 }
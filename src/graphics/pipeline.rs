@@ -1,13 +1,14 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap};
 use wgpu::{
     BindGroup, BindGroupLayout, BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, ColorTargetState,
-    CompareFunction, DepthBiasState, DepthStencilState, Device, FragmentState, MultisampleState, PipelineLayoutDescriptor,
-    PrimitiveState, PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource,
-    ShaderStages, TextureFormat, TextureSampleType, TextureViewDimension, VertexState,
+    CompareFunction, DepthBiasState, DepthStencilState, Device, FragmentState, FrontFace, MultisampleState, PipelineLayoutDescriptor,
+    PipelineLayout, PrimitiveState, PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureFormat, TextureSampleType, TextureViewDimension, VertexState,
 };
 
 use crate::graphics::model::Vertex;
 
+#[derive(Debug, Clone)]
 pub struct Layouts {
     pub camera_bgl: BindGroupLayout,
     pub model_bgl: BindGroupLayout,
@@ -28,18 +29,35 @@ pub fn create_bind_group_layouts(device: &Device) -> Layouts {
             count: None,
         }],
     });
+    let storage = |binding| BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::VERTEX,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
     let model_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("model_bgl"),
-        entries: &[BindGroupLayoutEntry {
-            binding: 0,
-            visibility: ShaderStages::VERTEX,
-            ty: BindingType::Buffer {
-                ty: BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
             },
-            count: None,
-        }],
+            // Morph target deltas and weights, skin influences and joint matrices.
+            storage(1),
+            storage(2),
+            storage(3),
+            storage(4),
+        ],
     });
     let material_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("material_bgl"),
@@ -60,6 +78,16 @@ pub fn create_bind_group_layouts(device: &Device) -> Layouts {
                 ty: BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
             },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ],
     });
     Layouts { camera_bgl, model_bgl, material_bgl }
@@ -69,7 +97,7 @@ pub fn create_pipeline(
     device: &Device,
     swap_chain_format: TextureFormat,
     layouts: &Layouts,
-) -> (RenderPipeline, BindGroup, Buffer, BindGroupLayout) {
+) -> (HashMap<(PrimitiveTopology, FrontFace), RenderPipeline>, BindGroup, Buffer) {
     let shader = device.create_shader_module(ShaderModuleDescriptor {
         label: None,
         source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shader.wgsl"))),
@@ -93,17 +121,42 @@ pub fn create_pipeline(
         push_constant_ranges: &[],
     });
 
-    let rp = device.create_render_pipeline(&RenderPipelineDescriptor {
+    let topologies = [
+        PrimitiveTopology::PointList,
+        PrimitiveTopology::LineList,
+        PrimitiveTopology::LineStrip,
+        PrimitiveTopology::TriangleList,
+        PrimitiveTopology::TriangleStrip,
+    ];
+    // Clockwise variants draw meshes mirrored by their node transform.
+    let pipelines = topologies
+        .into_iter()
+        .flat_map(|t| [(t, FrontFace::Ccw), (t, FrontFace::Cw)])
+        .map(|(t, f)| ((t, f), create_render_pipeline(device, &shader, &layout, swap_chain_format, t, f)))
+        .collect();
+
+    (pipelines, camera_bg, camera_buf)
+}
+
+fn create_render_pipeline(
+    device: &Device,
+    shader: &ShaderModule,
+    layout: &PipelineLayout,
+    swap_chain_format: TextureFormat,
+    topology: PrimitiveTopology,
+    front_face: FrontFace,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
         label: None,
-        layout: Some(&layout),
+        layout: Some(layout),
         vertex: VertexState {
-            module: &shader,
+            module: shader,
             entry_point: Some("vs_main"),
             buffers: &[Vertex::layout()],
             compilation_options: Default::default(),
         },
         fragment: Some(FragmentState {
-            module: &shader,
+            module: shader,
             entry_point: Some("fs_main"),
             targets: &[Some(ColorTargetState {
                 format: swap_chain_format,
@@ -113,8 +166,11 @@ pub fn create_pipeline(
             compilation_options: Default::default(),
         }),
         primitive: PrimitiveState {
-            topology: PrimitiveTopology::TriangleList,
-            cull_mode: Some(wgpu::Face::Back),
+            topology,
+            strip_index_format: topology.is_strip().then_some(wgpu::IndexFormat::Uint32),
+            front_face,
+            cull_mode: matches!(topology, PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip)
+                .then_some(wgpu::Face::Back),
             ..Default::default()
         },
         depth_stencil: Some(DepthStencilState {
@@ -127,7 +183,5 @@ pub fn create_pipeline(
         multisample: MultisampleState::default(),
         multiview: None,
         cache: None,
-    });
-
-    (rp, camera_bg, camera_buf, layouts.model_bgl.clone())
+    })
 }
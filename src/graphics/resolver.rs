@@ -0,0 +1,79 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+
+// Supplies the bytes behind external buffer and image URIs. Data URIs never reach
+// a resolver; the loader decodes those itself.
+pub trait ResourceResolver {
+    fn resolve(&mut self, uri: &str) -> io::Result<Vec<u8>>;
+}
+
+// Resolves relative URIs against a directory on disk and remembers every file it read.
+#[derive(Debug, Clone)]
+pub struct FsResolver {
+    base: PathBuf,
+    resolved: Vec<PathBuf>,
+}
+
+impl FsResolver {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into(), resolved: Vec::new() }
+    }
+
+    pub fn resolved(&self) -> &[PathBuf] {
+        &self.resolved
+    }
+
+    pub fn path_for(&self, uri: &str) -> io::Result<PathBuf> {
+        if uri.contains("://") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("unsupported URI scheme in {uri}")));
+        }
+        let decoded = urlencoding::decode(uri).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(self.base.join(Path::new(decoded.as_ref())))
+    }
+}
+
+impl ResourceResolver for FsResolver {
+    fn resolve(&mut self, uri: &str) -> io::Result<Vec<u8>> {
+        let path = self.path_for(uri)?;
+        let bytes = std::fs::read(&path)?;
+        self.resolved.push(path);
+        Ok(bytes)
+    }
+}
+
+// For self-contained assets: every external reference is an error.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoResolver;
+
+impl ResourceResolver for NoResolver {
+    fn resolve(&mut self, uri: &str) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("no resolver for external resource {uri}")))
+    }
+}
+
+// Returns the payload of a `data:` URI, or the resolver's bytes for anything else.
+pub fn read_uri(uri: &str, resolver: &mut dyn ResourceResolver) -> io::Result<Vec<u8>> {
+    let Some(rest) = uri.strip_prefix("data:") else {
+        return resolver.resolve(uri);
+    };
+    let (header, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "data URI without payload"))?;
+    if header.ends_with(";base64") {
+        base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    } else {
+        Ok(urlencoding::decode_binary(payload.as_bytes()).into_owned())
+    }
+}
+
+pub fn data_uri_mime_type(uri: &str) -> Option<&str> {
+    let header = uri.strip_prefix("data:")?.split(',').next()?;
+    header.split(';').next().filter(|m| !m.is_empty())
+}
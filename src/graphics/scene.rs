@@ -0,0 +1,260 @@
+use glam::{Mat4, Quat, Vec3};
+use crate::graphics::error::LoadError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<gltf::scene::Transform> for Transform {
+    fn from(t: gltf::scene::Transform) -> Self {
+        let (translation, rotation, scale) = t.decomposed();
+        Self {
+            translation: Vec3::from(translation),
+            rotation: Quat::from_array(rotation),
+            scale: Vec3::from(scale),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+    local: Transform,
+    world: Mat4,
+    dirty: bool,
+    // Morph target weights of the node's mesh; may be shorter than its target count.
+    weights: Vec<f32>,
+    weights_dirty: bool,
+}
+
+#[allow(dead_code)]
+impl Node {
+    pub fn local(&self) -> Transform {
+        self.local
+    }
+
+    pub fn world(&self) -> Mat4 {
+        self.world
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+// Joints are node indices; `inverse_bind_matrices` has one matrix per joint.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Skin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+impl Skin {
+    // Takes bind-pose vertices to where the joints are now. The skinned mesh's own node
+    // transform does not apply, as the glTF spec requires.
+    pub fn joint_matrices(&self, graph: &SceneGraph) -> Vec<Mat4> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| graph.nodes[joint].world * *inverse_bind)
+            .collect()
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub name: Option<String>,
+    pub roots: Vec<usize>,
+}
+
+// Nodes live in one arena shared by every scene, indexed like the glTF document.
+#[derive(Debug, Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    pub scenes: Vec<Scene>,
+    active: Option<usize>,
+}
+
+#[allow(dead_code)]
+impl SceneGraph {
+    pub fn from_document(doc: &gltf::Document) -> Result<Self, LoadError> {
+        let mut nodes: Vec<Node> = doc
+            .nodes()
+            .map(|n| Node {
+                name: n.name().map(str::to_owned),
+                parent: None,
+                children: n.children().map(|c| c.index()).collect(),
+                mesh: n.mesh().map(|m| m.index()),
+                skin: n.skin().map(|s| s.index()),
+                camera: n.camera().map(|c| c.index()),
+                light: n.light().map(|l| l.index()),
+                local: n.transform().into(),
+                world: Mat4::IDENTITY,
+                dirty: true,
+                weights: n.weights().or_else(|| n.mesh()?.weights()).unwrap_or_default().to_vec(),
+                weights_dirty: false,
+            })
+            .collect();
+        for ix in 0..nodes.len() {
+            for c in nodes[ix].children.clone() {
+                if nodes[c].parent.replace(ix).is_some() {
+                    return Err(LoadError::NodeCycle { node: c });
+                }
+            }
+        }
+        // With at most one parent each, nodes that no parentless node reaches form cycles.
+        let mut reached = vec![false; nodes.len()];
+        let mut stack: Vec<usize> = (0..nodes.len()).filter(|&ix| nodes[ix].parent.is_none()).collect();
+        while let Some(ix) = stack.pop() {
+            reached[ix] = true;
+            stack.extend(&nodes[ix].children);
+        }
+        if let Some(node) = reached.iter().position(|r| !r) {
+            return Err(LoadError::NodeCycle { node });
+        }
+        let scenes = doc
+            .scenes()
+            .map(|s| Scene {
+                name: s.name().map(str::to_owned),
+                roots: s.nodes().map(|n| n.index()).collect(),
+            })
+            .collect();
+        let active = doc
+            .default_scene()
+            .map(|s| s.index())
+            .or_else(|| (doc.scenes().len() > 0).then_some(0));
+
+        let mut graph = Self { nodes, scenes, active };
+        graph.update_world_transforms();
+        Ok(graph)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn node(&self, ix: usize) -> Option<&Node> {
+        self.nodes.get(ix)
+    }
+
+    pub fn node_mut(&mut self, ix: usize) -> Option<&mut Node> {
+        self.nodes.get_mut(ix)
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name.as_deref() == Some(name))
+    }
+
+    pub fn active_scene(&self) -> Option<usize> {
+        self.active
+    }
+
+    pub fn set_active_scene(&mut self, ix: usize) -> bool {
+        if ix >= self.scenes.len() {
+            return false;
+        }
+        self.active = Some(ix);
+        true
+    }
+
+    // Nodes of the active scene, parents before children.
+    pub fn active_nodes(&self) -> Vec<usize> {
+        match self.active {
+            Some(ix) => self.traverse(&self.scenes[ix].roots),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn set_local_transform(&mut self, ix: usize, local: Transform) {
+        if let Some(node) = self.nodes.get_mut(ix) {
+            node.local = local;
+            node.dirty = true;
+        }
+    }
+
+    pub fn set_weights(&mut self, ix: usize, weights: &[f32]) {
+        if let Some(node) = self.nodes.get_mut(ix) {
+            node.weights.clear();
+            node.weights.extend_from_slice(weights);
+            node.weights_dirty = true;
+        }
+    }
+
+    // Indices of nodes whose morph weights were set since the last call.
+    pub fn take_changed_weights(&mut self) -> Vec<usize> {
+        self.nodes
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(ix, n)| std::mem::take(&mut n.weights_dirty).then_some(ix))
+            .collect()
+    }
+
+    // Every node reachable from `roots`, parents before children.
+    pub fn traverse(&self, roots: &[usize]) -> Vec<usize> {
+        let mut out = Vec::new();
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = roots.iter().rev().copied().collect();
+        while let Some(ix) = stack.pop() {
+            if std::mem::replace(&mut visited[ix], true) {
+                continue;
+            }
+            out.push(ix);
+            stack.extend(self.nodes[ix].children.iter().rev().copied());
+        }
+        out
+    }
+
+    // Recomputes world matrices below any node whose local transform changed and
+    // returns the indices of every node whose world matrix was rewritten.
+    pub fn update_world_transforms(&mut self) -> Vec<usize> {
+        let mut changed = Vec::new();
+        let mut stack: Vec<(usize, Mat4, bool)> = (0..self.nodes.len())
+            .rev()
+            .filter(|&ix| self.nodes[ix].parent.is_none())
+            .map(|ix| (ix, Mat4::IDENTITY, false))
+            .collect();
+        while let Some((ix, parent_world, parent_dirty)) = stack.pop() {
+            let node = &mut self.nodes[ix];
+            let dirty = parent_dirty || node.dirty;
+            if dirty {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+                changed.push(ix);
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().rev().map(|&c| (c, world, dirty)));
+        }
+        changed
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+use half::f16;
+use image::{ImageBuffer, Pixel, Rgba32FImage, RgbaImage};
+use wgpu::{
+    util::{DeviceExt, TextureDataOrder},
+    AddressMode, Device, Features, FilterMode, Queue, Sampler, SamplerDescriptor, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+type Rgba16Image = ImageBuffer<image::Rgba<u16>, Vec<u16>>;
+
+// Formats that keep 16-bit and float images at their decoded precision.
+pub const PRECISION_FEATURES: Features = Features::TEXTURE_FORMAT_16BIT_NORM.union(Features::FLOAT32_FILTERABLE);
+
+// Everything a glTF sampler can express. `mipmap_filter` is None for min filters
+// that sample the base level only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerKey {
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: Option<FilterMode>,
+    pub wrap_u: AddressMode,
+    pub wrap_v: AddressMode,
+}
+
+impl Default for SamplerKey {
+    // Filters are left to the implementation when a glTF sampler omits them; use trilinear.
+    fn default() -> Self {
+        Self {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: Some(FilterMode::Linear),
+            wrap_u: AddressMode::Repeat,
+            wrap_v: AddressMode::Repeat,
+        }
+    }
+}
+
+impl From<&gltf::texture::Sampler<'_>> for SamplerKey {
+    fn from(s: &gltf::texture::Sampler<'_>) -> Self {
+        let defaults = Self::default();
+        let mag_filter = match s.mag_filter() {
+            Some(MagFilter::Nearest) => FilterMode::Nearest,
+            Some(MagFilter::Linear) => FilterMode::Linear,
+            None => defaults.mag_filter,
+        };
+        let (min_filter, mipmap_filter) = match s.min_filter() {
+            Some(MinFilter::Nearest) => (FilterMode::Nearest, None),
+            Some(MinFilter::Linear) => (FilterMode::Linear, None),
+            Some(MinFilter::NearestMipmapNearest) => (FilterMode::Nearest, Some(FilterMode::Nearest)),
+            Some(MinFilter::LinearMipmapNearest) => (FilterMode::Linear, Some(FilterMode::Nearest)),
+            Some(MinFilter::NearestMipmapLinear) => (FilterMode::Nearest, Some(FilterMode::Linear)),
+            Some(MinFilter::LinearMipmapLinear) => (FilterMode::Linear, Some(FilterMode::Linear)),
+            None => (defaults.min_filter, defaults.mipmap_filter),
+        };
+        Self {
+            mag_filter,
+            min_filter,
+            mipmap_filter,
+            wrap_u: address_mode(s.wrap_s()),
+            wrap_v: address_mode(s.wrap_t()),
+        }
+    }
+}
+
+fn address_mode(mode: WrappingMode) -> AddressMode {
+    match mode {
+        WrappingMode::ClampToEdge => AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => AddressMode::Repeat,
+    }
+}
+
+// One wgpu sampler per distinct glTF sampler state, shared by every material of a model.
+#[derive(Debug, Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerKey, Sampler>,
+}
+
+impl SamplerCache {
+    pub fn get(&mut self, device: &Device, key: SamplerKey) -> Sampler {
+        self.samplers
+            .entry(key)
+            .or_insert_with(|| {
+                device.create_sampler(&SamplerDescriptor {
+                    label: Some("gltf_sampler"),
+                    address_mode_u: key.wrap_u,
+                    address_mode_v: key.wrap_v,
+                    address_mode_w: AddressMode::Repeat,
+                    mag_filter: key.mag_filter,
+                    min_filter: key.min_filter,
+                    mipmap_filter: key.mipmap_filter.unwrap_or(FilterMode::Nearest),
+                    // Without a mipmap filter only the base level may be sampled.
+                    lod_max_clamp: if key.mipmap_filter.is_some() { 32.0 } else { 0.0 },
+                    ..Default::default()
+                })
+            })
+            .clone()
+    }
+}
+
+// Decoded image contents at the precision they were stored in. Values are still
+// encoded as stored in the image, the sRGB transfer is applied on upload. `Native` is
+// already in a GPU format with its own mip levels, as read from KTX2, and is uploaded
+// untouched.
+#[derive(Debug, Clone)]
+pub enum TexturePixels {
+    Unorm8(RgbaImage),
+    Unorm16(Rgba16Image),
+    Float(Rgba32FImage),
+    Native { format: TextureFormat, width: u32, height: u32, mips: Vec<Vec<u8>> },
+}
+
+impl TexturePixels {
+    pub fn white() -> Self {
+        TexturePixels::Unorm8(RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])))
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            TexturePixels::Unorm8(img) => img.dimensions(),
+            TexturePixels::Unorm16(img) => img.dimensions(),
+            TexturePixels::Float(img) => img.dimensions(),
+            TexturePixels::Native { width, height, .. } => (*width, *height),
+        }
+    }
+
+    // 16-bit images upload as Rgba16Unorm and float images as Rgba32Float. Without the
+    // feature either needs, they fall back to Rgba16Float, which has an 11-bit mantissa
+    // and tops out at 65504.
+    pub fn gpu_format(&self, features: Features, srgb: bool) -> TextureFormat {
+        match self {
+            TexturePixels::Unorm8(_) if srgb => TextureFormat::Rgba8UnormSrgb,
+            TexturePixels::Unorm8(_) => TextureFormat::Rgba8Unorm,
+            TexturePixels::Unorm16(_) if features.contains(Features::TEXTURE_FORMAT_16BIT_NORM) => {
+                TextureFormat::Rgba16Unorm
+            }
+            TexturePixels::Float(_) if features.contains(Features::FLOAT32_FILTERABLE) => TextureFormat::Rgba32Float,
+            TexturePixels::Unorm16(_) | TexturePixels::Float(_) => TextureFormat::Rgba16Float,
+            TexturePixels::Native { format, .. } => *format,
+        }
+    }
+
+    // The feature whose absence makes `gpu_format` lose precision, if any.
+    pub fn missing_precision_feature(&self, features: Features) -> Option<Features> {
+        let needed = match self {
+            TexturePixels::Unorm16(_) => Features::TEXTURE_FORMAT_16BIT_NORM,
+            TexturePixels::Float(_) => Features::FLOAT32_FILTERABLE,
+            _ => return None,
+        };
+        (!features.contains(needed)).then_some(needed)
+    }
+}
+
+// Uploads the image with its full mip chain in `gpu_format`. 8-bit color uses an sRGB
+// format; the wider formats have no sRGB variant, so their color is linearized here.
+// Native images keep the format and levels they came with.
+fn create_texture(device: &Device, queue: &Queue, label: &str, pixels: &TexturePixels, srgb: bool) -> Texture {
+    let (width, height) = pixels.dimensions();
+    let mut mip_level_count = mip_level_count(width, height);
+    let format = pixels.gpu_format(device.features(), srgb);
+    let data: Vec<u8> = match pixels {
+        TexturePixels::Unorm8(img) => generate_mips(img, srgb).into_iter().flat_map(|level| level.into_raw()).collect(),
+        TexturePixels::Unorm16(img) => {
+            let values = Rgba32FImage::from_fn(img.width(), img.height(), |x, y| {
+                image::Rgba(img.get_pixel(x, y).0.map(|c| c as f32 / 65535.0))
+            });
+            encode_wide(values, srgb, format)
+        }
+        TexturePixels::Float(img) => encode_wide(img.clone(), srgb, format),
+        TexturePixels::Native { mips, .. } => {
+            mip_level_count = mips.len() as u32;
+            mips.concat()
+        }
+    };
+    device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &data,
+    )
+}
+
+// One texture per decoded image and color space, shared by every material of a model.
+// Images that failed to decode, and materials without a texture, get a white texel.
+#[derive(Debug)]
+pub struct TextureCache {
+    images: Vec<Option<TexturePixels>>,
+    views: HashMap<(Option<usize>, bool), TextureView>,
+}
+
+impl TextureCache {
+    pub fn new(images: Vec<Option<TexturePixels>>) -> Self {
+        Self { images, views: HashMap::new() }
+    }
+
+    pub fn is_loaded(&self, image: usize) -> bool {
+        self.images.get(image).is_some_and(Option::is_some)
+    }
+
+    pub fn get(&mut self, device: &Device, queue: &Queue, image: Option<usize>, srgb: bool) -> TextureView {
+        let pixels = image.and_then(|ix| self.images.get(ix)?.as_ref());
+        let key = (image.filter(|_| pixels.is_some()), srgb);
+        self.views
+            .entry(key)
+            .or_insert_with(|| {
+                let white = TexturePixels::white();
+                let label = match key.0 {
+                    Some(ix) => format!("gltf_image_{ix}"),
+                    None => "white".to_owned(),
+                };
+                create_texture(device, queue, &label, pixels.unwrap_or(&white), srgb)
+                    .create_view(&TextureViewDescriptor::default())
+            })
+            .clone()
+    }
+}
+
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+// The full chain down to 1x1, base level first. Each level is a 2x2 box filter of the
+// previous one; color channels of sRGB textures are averaged in linear space.
+pub fn generate_mips(base: &RgbaImage, srgb: bool) -> Vec<RgbaImage> {
+    let to_linear: [f32; 256] = std::array::from_fn(|i| {
+        let v = i as f32 / 255.0;
+        if srgb { srgb_to_linear(v) } else { v }
+    });
+    let encode = |v: f32| if srgb { linear_to_srgb(v) } else { v };
+    mip_chain(base, |p| {
+        let mut sum = [0.0f32; 4];
+        for px in p {
+            for c in 0..3 {
+                sum[c] += to_linear[px[c] as usize];
+            }
+            sum[3] += px[3] as f32 / 255.0;
+        }
+        image::Rgba([
+            to_unorm8(encode(sum[0] / 4.0)),
+            to_unorm8(encode(sum[1] / 4.0)),
+            to_unorm8(encode(sum[2] / 4.0)),
+            to_unorm8(sum[3] / 4.0),
+        ])
+    })
+}
+
+// Float images are already linear, so every channel is a plain average.
+// Linearizes color if needed and builds the mip chain in f32 before storing it in the
+// wide `format` chosen for the image.
+fn encode_wide(mut linear: Rgba32FImage, srgb: bool, format: TextureFormat) -> Vec<u8> {
+    if srgb {
+        for p in linear.pixels_mut() {
+            for c in &mut p.0[..3] {
+                *c = srgb_to_linear(*c);
+            }
+        }
+    }
+    let values = generate_mips_f32(&linear).into_iter().flat_map(|level| level.into_raw());
+    match format {
+        TextureFormat::Rgba16Unorm => {
+            let units: Vec<u16> = values.map(|v| (v.clamp(0.0, 1.0) * 65535.0).round() as u16).collect();
+            bytemuck::cast_slice(&units).to_vec()
+        }
+        TextureFormat::Rgba32Float => bytemuck::cast_slice(&values.collect::<Vec<f32>>()).to_vec(),
+        _ => {
+            let halves: Vec<u16> = values.map(|v| f16::from_f32(v).to_bits()).collect();
+            bytemuck::cast_slice(&halves).to_vec()
+        }
+    }
+}
+
+fn generate_mips_f32(base: &Rgba32FImage) -> Vec<Rgba32FImage> {
+    mip_chain(base, |p| image::Rgba(std::array::from_fn(|c| p.iter().map(|px| px[c]).sum::<f32>() / 4.0)))
+}
+
+fn mip_chain<P: Pixel>(base: &ImageBuffer<P, Vec<P::Subpixel>>, average: impl Fn([P; 4]) -> P) -> Vec<ImageBuffer<P, Vec<P::Subpixel>>> {
+    let mut levels = vec![base.clone()];
+    while let Some(prev) = levels.last()
+        && (prev.width() > 1 || prev.height() > 1)
+    {
+        let (w, h) = ((prev.width() / 2).max(1), (prev.height() / 2).max(1));
+        let next = ImageBuffer::from_fn(w, h, |x, y| {
+            let (x0, x1) = (2 * x, (2 * x + 1).min(prev.width() - 1));
+            let (y0, y1) = (2 * y, (2 * y + 1).min(prev.height() - 1));
+            average([*prev.get_pixel(x0, y0), *prev.get_pixel(x1, y0), *prev.get_pixel(x0, y1), *prev.get_pixel(x1, y1)])
+        });
+        levels.push(next);
+    }
+    levels
+}
+
+fn to_unorm8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 }
+}
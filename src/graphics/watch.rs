@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use winit::event_loop::EventLoopProxy;
+
+use crate::graphics::GraphicsEvent;
+
+// Editors and exporters often replace files instead of writing them in place, so the
+// parent directories are watched and events filtered down to the model's own files.
+pub fn watch_sources(sources: &[PathBuf], proxy: EventLoopProxy<GraphicsEvent>) -> notify::Result<RecommendedWatcher> {
+    let files: Vec<PathBuf> = sources.iter().map(|p| p.canonicalize().unwrap_or_else(|_| p.clone())).collect();
+    let mut dirs: Vec<PathBuf> = files.iter().filter_map(|p| p.parent().map(PathBuf::from)).collect();
+    dirs.sort();
+    dirs.dedup();
+
+    let watched = files.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        if event.paths.iter().any(|p| watched.contains(p)) {
+            let _ = proxy.send_event(GraphicsEvent::SourceChanged);
+        }
+    })?;
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok(watcher)
+}
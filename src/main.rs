@@ -1,19 +1,62 @@
 mod app;
 mod graphics;
 
-use crate::{app::App, graphics::Graphics};
+use crate::{app::App, graphics::{GraphicsEvent, LoadOptions, NormalMode}};
+use std::path::PathBuf;
+
 use winit::event_loop::{ControlFlow, EventLoop, DeviceEvents};
 
-fn run_app(event_loop: EventLoop<Graphics>, mut app: App) {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error")).init();
+const DEFAULT_MODEL: &str = "assets/BoomBox.glb";
+const DEFAULT_SMOOTH_ANGLE: f32 = 60.0;
+const USAGE: &str = "usage: wgpu-gltf [--smooth-normals[=DEGREES]] [--animation=NAME] [MODEL]";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    std::process::exit(2);
+}
+
+fn parse_args() -> (PathBuf, LoadOptions, Option<String>) {
+    let mut model_path = None;
+    let mut options = LoadOptions::default();
+    let mut animation = None;
+    for arg in std::env::args_os().skip(1) {
+        if let Some(name) = arg.to_str().and_then(|a| a.strip_prefix("--animation=")) {
+            animation = Some(name.to_owned());
+            continue;
+        }
+        match arg.to_str().and_then(|a| a.strip_prefix("--smooth-normals")) {
+            Some("") => options.normals = NormalMode::Smooth { max_angle_deg: DEFAULT_SMOOTH_ANGLE },
+            Some(angle) => {
+                let max_angle_deg = angle
+                    .strip_prefix('=')
+                    .and_then(|a| a.parse().ok())
+                    .unwrap_or_else(|| usage_error(&format!("invalid --smooth-normals{angle}, expected --smooth-normals=DEGREES")));
+                options.normals = NormalMode::Smooth { max_angle_deg };
+            }
+            None if arg.to_string_lossy().starts_with("--") => {
+                usage_error(&format!("unknown option {}", arg.to_string_lossy()))
+            }
+            None if model_path.is_some() => {
+                usage_error(&format!("unexpected argument {}, only one MODEL is accepted", arg.to_string_lossy()))
+            }
+            None => model_path = Some(PathBuf::from(arg)),
+        }
+    }
+    (model_path.unwrap_or_else(|| PathBuf::from(DEFAULT_MODEL)), options, animation)
+}
+
+fn run_app(event_loop: EventLoop<GraphicsEvent>, mut app: App) {
+    // Load warnings are logged by this crate; dependencies stay at errors.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error,wgpu_gltf=warn")).init();
     let _ = event_loop.run_app(&mut app);
 }
 
 fn main() {
-    let event_loop = EventLoop::<Graphics>::with_user_event().build().unwrap();
+    let (model_path, load_options, animation) = parse_args();
+    let event_loop = EventLoop::<GraphicsEvent>::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
     event_loop.listen_device_events(DeviceEvents::Always);
 
-    let app = App::new(&event_loop);
+    let app = App::new(&event_loop, model_path, load_options, animation);
     run_app(event_loop, app);
 }
@@ -8,33 +8,104 @@ struct ModelXform {
 }
 @group(1) @binding(0) var<uniform> model_xform : ModelXform;
 
+// Position, normal and tangent delta of every vertex, target after target.
+@group(1) @binding(1) var<storage, read> morph_targets : array<vec4<f32>>;
+struct MorphWeights {
+  target_count : u32,
+  vertex_count : u32,
+  weights : array<f32>,
+}
+@group(1) @binding(2) var<storage, read> morph : MorphWeights;
+
+// One JOINTS_n/WEIGHTS_n set; each vertex has `influence_sets` of them in a row.
+struct Influence {
+  joints : vec4<u32>,
+  weights : vec4<f32>,
+}
+@group(1) @binding(3) var<storage, read> skin_influences : array<Influence>;
+struct Skin {
+  influence_sets : u32,
+  joint_matrices : array<mat4x4<f32>>,
+}
+@group(1) @binding(4) var<storage, read> skin : Skin;
+
+fn skin_matrix(vertex : u32) -> mat4x4<f32> {
+  var m = mat4x4<f32>();
+  var total = 0.0;
+  for (var s = 0u; s < skin.influence_sets; s++) {
+    let influence = skin_influences[vertex * skin.influence_sets + s];
+    for (var i = 0u; i < 4u; i++) {
+      m += skin.joint_matrices[influence.joints[i]] * influence.weights[i];
+      total += influence.weights[i];
+    }
+  }
+  // Unskinned meshes, and vertices no joint influences, stay where they are.
+  if (total == 0.0) {
+    return mat4x4<f32>(vec4(1.0, 0.0, 0.0, 0.0), vec4(0.0, 1.0, 0.0, 0.0), vec4(0.0, 0.0, 1.0, 0.0), vec4(0.0, 0.0, 0.0, 1.0));
+  }
+  return m;
+}
+
 @group(2) @binding(0) var texBase : texture_2d<f32>;
 @group(2) @binding(1) var samp    : sampler;
 
+// texcoord fields select TEXCOORD_0 or TEXCOORD_1 for the matching texture.
+struct MaterialParams {
+  base_color_texcoord : u32,
+}
+@group(2) @binding(2) var<uniform> material : MaterialParams;
+
 struct VsIn {
   @location(0) pos : vec3<f32>,
   @location(1) nrm : vec3<f32>,
   @location(2) uv  : vec2<f32>,
+  @location(3) tan : vec4<f32>,
+  @location(4) uv1 : vec2<f32>,
+  @location(5) color : vec4<f32>,
 }
 struct VsOut {
   @builtin(position) pos : vec4<f32>,
   @location(0) nrm : vec3<f32>,
   @location(1) uv  : vec2<f32>,
+  @location(2) tan : vec4<f32>,
+  @location(3) uv1 : vec2<f32>,
+  @location(4) color : vec4<f32>,
 }
 
 @vertex
-fn vs_main(in: VsIn) -> VsOut {
+fn vs_main(in: VsIn, @builtin(vertex_index) vertex : u32) -> VsOut {
+  var pos = in.pos;
+  var nrm = in.nrm;
+  var tan = in.tan.xyz;
+  for (var t = 0u; t < morph.target_count; t++) {
+    let w = morph.weights[t];
+    if (w != 0.0) {
+      let base = (t * morph.vertex_count + vertex) * 3u;
+      pos += w * morph_targets[base].xyz;
+      nrm += w * morph_targets[base + 1u].xyz;
+      tan += w * morph_targets[base + 2u].xyz;
+    }
+  }
+
+  let model = model_xform.model * skin_matrix(vertex);
   var out: VsOut;
-  let world = model_xform.model * vec4<f32>(in.pos, 1.0);
+  let world = model * vec4<f32>(pos, 1.0);
   out.pos = camera.view_proj * world;
-  out.nrm = normalize((model_xform.model * vec4<f32>(in.nrm, 0.0)).xyz);
+  out.nrm = normalize((model * vec4<f32>(nrm, 0.0)).xyz);
   out.uv = in.uv;
+  out.uv1 = in.uv1;
+  out.color = in.color;
+  out.tan = vec4<f32>(normalize((model * vec4<f32>(tan, 0.0)).xyz), in.tan.w);
   return out;
 }
 
+fn texcoord(in: VsOut, index: u32) -> vec2<f32> {
+  return select(in.uv, in.uv1, index == 1u);
+}
+
 @fragment
 fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
-  let base = textureSample(texBase, samp, in.uv);
+  let base = textureSample(texBase, samp, texcoord(in, material.base_color_texcoord)) * in.color;
   let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.3));
   let lambert = max(dot(normalize(in.nrm), light_dir), 0.1);
   return vec4<f32>(base.rgb * lambert, base.a);